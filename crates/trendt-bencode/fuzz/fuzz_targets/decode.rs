@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use trendt_bencode::{DecoderOptions, Value, decode, decode_ref, decode_with_options};
+
+fuzz_target!(|data: &[u8]| {
+    // The owned and borrowing decoders must agree on every input
+    let owned = decode(data);
+    let borrowed = decode_ref(data);
+    assert_eq!(owned.is_ok(), borrowed.is_ok());
+    if let (Ok(owned), Ok(borrowed)) = (owned, borrowed) {
+        assert_eq!(owned, Value::from(borrowed));
+    }
+
+    let _ = decode_with_options(data, DecoderOptions::lenient());
+});
@@ -0,0 +1,20 @@
+//! Shares its seeds with the decode target:
+//! `cargo fuzz run round_trip corpus/decode`
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use trendt_bencode::{DecoderOptions, decode, decode_with_options, encode};
+
+fuzz_target!(|data: &[u8]| {
+    // Strict decoding only accepts canonical input, which encodes back unchanged
+    if let Ok(value) = decode(data) {
+        assert_eq!(encode(&value), data);
+    }
+
+    // Whatever lenient decoding accepts must encode to canonical bencode
+    if let Ok(value) = decode_with_options(data, DecoderOptions::lenient()) {
+        let encoded = encode(&value);
+        assert_eq!(decode(&encoded).unwrap(), value);
+    }
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use trendt_torrent::Torrent;
+
+fuzz_target!(|data: &[u8]| {
+    // Go through the validating constructor so the v2 and hybrid checks run too
+    if let Ok(torrent) = Torrent::from_bytes(data) {
+        let _ = torrent.to_bytes();
+        let _ = torrent.to_magnet();
+    }
+});
@@ -0,0 +1,184 @@
+//! Framing for bencoded messages over tokio streams
+//!
+//! Bencode values are self-delimiting, so no length prefix is needed: the
+//! codec scans incoming bytes until one complete value has arrived and then
+//! deserializes it. Protocols that already put a length prefix in front of
+//! each payload can split frames with `tokio_util`'s `LengthDelimitedCodec`
+//! and pass each frame to [`from_bytes`](crate::from_bytes).
+
+use std::marker::PhantomData;
+
+use bytes::{BufMut, BytesMut};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::de::from_bytes_with_options;
+use crate::error::{Error, KeyPath, Result};
+use crate::options::DecoderOptions;
+use crate::push::FrameScanner;
+use crate::ser::to_writer;
+use crate::value::Value;
+
+/// A codec that reads and writes one bencoded value per frame
+///
+/// Frames decode into `T`, which defaults to [`Value`]; any deserializable
+/// type works. Anything serializable can be encoded. The depth, length, item
+/// and buffer limits in the [`DecoderOptions`] are enforced while the frame
+/// is still arriving, so a peer cannot make the codec buffer more than
+/// `max_buffered` bytes.
+///
+/// After an error the stream cannot be resynchronised, and the connection
+/// should be dropped.
+#[derive(Debug)]
+pub struct BencodeCodec<T = Value> {
+    /// Finds where the frame at the front of the buffer ends
+    scanner: FrameScanner,
+    /// The type frames decode into
+    item: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for BencodeCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> BencodeCodec<T> {
+    /// Create a new codec
+    pub fn new() -> Self {
+        Self::with_options(DecoderOptions::default())
+    }
+
+    /// Create a new codec with custom validation rules and limits
+    pub fn with_options(options: DecoderOptions) -> Self {
+        BencodeCodec {
+            scanner: FrameScanner::new(options),
+            item: PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Decoder for BencodeCodec<T> {
+    type Item = T;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>> {
+        let result = self.scanner.scan(src);
+        if let Ok(None) = result {
+            return Ok(None);
+        }
+        self.scanner.reset();
+        let frame = src.split_to(result?.unwrap_or_default());
+        from_bytes_with_options(&frame, self.scanner.options).map(Some)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<T>> {
+        match self.decode(src)? {
+            Some(item) => Ok(Some(item)),
+            None if src.is_empty() => Ok(None),
+            None => Err(KeyPath::default().locate(Error::UnexpectedEof, src.len())),
+        }
+    }
+}
+
+impl<T, I: Serialize> Encoder<I> for BencodeCodec<T> {
+    type Error = Error;
+
+    fn encode(&mut self, item: I, dst: &mut BytesMut) -> Result<()> {
+        to_writer(dst.writer(), &item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::decode::decode;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Ping {
+        id: String,
+        seq: u32,
+    }
+
+    #[test]
+    fn frames_arrive_in_pieces() {
+        let mut codec = BencodeCodec::<Value>::new();
+        let mut buffer = BytesMut::new();
+        buffer.extend_from_slice(b"d3:fooi1");
+        assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+        buffer.extend_from_slice(b"ee4:spami");
+        assert_eq!(
+            codec.decode(&mut buffer).unwrap(),
+            Some(decode(b"d3:fooi1ee").unwrap())
+        );
+        assert_eq!(
+            codec.decode(&mut buffer).unwrap(),
+            Some(Value::ByteString(b"spam".to_vec()))
+        );
+        assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+        assert_eq!(&buffer[..], b"i");
+    }
+
+    #[test]
+    fn typed_round_trip() {
+        let mut codec = BencodeCodec::<Ping>::new();
+        let mut buffer = BytesMut::new();
+        let ping = Ping {
+            id: "abc".into(),
+            seq: 7,
+        };
+        codec.encode(&ping, &mut buffer).unwrap();
+        codec.encode(&ping, &mut buffer).unwrap();
+        assert_eq!(&buffer[..], b"d2:id3:abc3:seqi7eed2:id3:abc3:seqi7ee");
+        assert_eq!(codec.decode(&mut buffer).unwrap(), Some(ping));
+        assert!(codec.decode(&mut buffer).unwrap().is_some());
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        let options = DecoderOptions {
+            max_string_length: 10,
+            ..DecoderOptions::strict()
+        };
+        let mut codec = BencodeCodec::<Value>::with_options(options);
+        let err = codec.decode(&mut BytesMut::from(&b"100:"[..])).unwrap_err();
+        assert!(matches!(err.inner(), Error::LengthLimitExceeded(100)));
+
+        let err = codec
+            .decode(&mut BytesMut::from(&b"d1:bi1e1:ai2ee"[..]))
+            .unwrap_err();
+        assert!(matches!(err.inner(), Error::UnsortedDictKeys));
+
+        let mut codec = BencodeCodec::<Ping>::new();
+        let err = codec.decode(&mut BytesMut::from(&b"i1e"[..])).unwrap_err();
+        assert!(matches!(err.inner(), Error::InvalidCharacter(b'i')));
+
+        let err = codec
+            .decode_eof(&mut BytesMut::from(&b"d2:id"[..]))
+            .unwrap_err();
+        assert!(matches!(err.inner(), Error::UnexpectedEof));
+    }
+
+    #[test]
+    fn unterminated_frames_are_bounded() {
+        let options = DecoderOptions {
+            max_buffered: 1000,
+            ..DecoderOptions::lenient()
+        };
+        let mut codec = BencodeCodec::<Value>::with_options(options);
+        let mut buffer = BytesMut::from(&b"l"[..]);
+        let err = loop {
+            buffer.extend_from_slice(b"i0e");
+            match codec.decode(&mut buffer) {
+                Ok(item) => assert_eq!(item, None),
+                Err(err) => break err,
+            }
+        };
+        assert!(matches!(err.inner(), Error::BufferLimitExceeded(_)));
+        assert!(buffer.len() <= 1003);
+    }
+}
@@ -1,17 +1,50 @@
+use std::str::FromStr;
+
 use serde::Deserialize;
 use serde::de::{self, Visitor};
 
-use crate::error::{Error, Result};
+use crate::decode::{Decoder, DictKeys, parse_length};
+use crate::error::{Error, KeyPath, Result};
+use crate::integer::{integer_text, out_of_range};
+use crate::options::DecoderOptions;
+use crate::raw;
 
 /// A deserializer for bencode data
 pub struct Deserializer<'de> {
     input: &'de [u8],
     position: usize,
+    options: DecoderOptions,
+    depth: usize,
+    path: KeyPath<'de>,
 }
 
 impl<'de> Deserializer<'de> {
     pub fn new(input: &'de [u8]) -> Self {
-        Deserializer { input, position: 0 }
+        Self::with_options(input, DecoderOptions::default())
+    }
+
+    /// Create a deserializer with custom validation rules and limits
+    pub fn with_options(input: &'de [u8], options: DecoderOptions) -> Self {
+        Deserializer {
+            input,
+            position: 0,
+            options,
+            depth: 0,
+            path: KeyPath::default(),
+        }
+    }
+
+    /// Check that the whole input has been consumed
+    pub fn end(&self) -> Result<()> {
+        if self.position < self.input.len() {
+            return Err(self.locate(Error::TrailingData));
+        }
+        Ok(())
+    }
+
+    /// Annotate an error with the current position and key path
+    fn locate(&self, err: Error) -> Error {
+        self.path.locate(err, self.position)
     }
 
     fn peek(&self) -> Result<u8> {
@@ -28,25 +61,32 @@ impl<'de> Deserializer<'de> {
     }
 
     fn expect(&mut self, expected: u8) -> Result<()> {
-        let byte = self.next()?;
+        // Check before consuming so errors point at the offending byte
+        let byte = self.peek()?;
         if byte != expected {
             return Err(Error::InvalidCharacter(byte));
         }
+        self.next()?;
         Ok(())
     }
 
-    fn parse_integer(&mut self) -> Result<i64> {
+    /// Parse an integer and return its validated digits
+    fn parse_integer(&mut self) -> Result<&'de str> {
         self.expect(b'i')?;
         let start = self.position;
         while self.peek()? != b'e' {
             self.next()?;
         }
         let end = self.position;
-        let bytes = &self.input[start..end];
-        let s = std::str::from_utf8(bytes).map_err(|_| Error::InvalidInteger)?;
-        let n: i64 = s.parse().map_err(|_| Error::InvalidInteger)?;
+        let text = integer_text(&self.input[start..end], &self.options)?;
         self.expect(b'e')?;
-        Ok(n)
+        Ok(text)
+    }
+
+    /// Parse an integer into `T`, failing if it is out of range rather than truncating
+    fn parse_int<T: FromStr>(&mut self, expected: &'static str) -> Result<T> {
+        let text = self.parse_integer()?;
+        text.parse().map_err(|_| out_of_range(text, expected))
     }
 
     fn parse_byte_string(&mut self) -> Result<&'de [u8]> {
@@ -55,25 +95,60 @@ impl<'de> Deserializer<'de> {
             self.next()?;
         }
         let end = self.position;
-        let len_bytes = &self.input[start..end];
-        let len_str = std::str::from_utf8(len_bytes).map_err(|_| Error::InvalidCharacter(0))?;
-        let len: usize = len_str.parse().map_err(|_| Error::InvalidCharacter(0))?;
+        let len = parse_length(&self.input[start..end], &self.options)?;
         self.expect(b':')?;
-        if self.position + len > self.input.len() {
+        if len > self.input.len() - self.position {
             return Err(Error::UnexpectedEof);
         }
         let data = &self.input[self.position..self.position + len];
         self.position += len;
         Ok(data)
     }
+
+    /// Validate and skip the next value, returning its encoded bytes
+    fn parse_raw(&mut self) -> Result<&'de [u8]> {
+        let rest = &self.input[self.position..];
+        let options = DecoderOptions {
+            max_depth: self.options.max_depth - self.depth,
+            ..self.options
+        };
+        let span = Decoder::with_options(rest, options)
+            .skip_value()
+            .map_err(|err| err.offset(self.position, &self.path))?;
+        self.position += span.end;
+        Ok(&rest[span])
+    }
+
+    /// Track entering a list or dictionary, enforcing the depth limit
+    fn enter(&mut self) -> Result<()> {
+        if self.depth >= self.options.max_depth {
+            return Err(Error::DepthLimitExceeded);
+        }
+        self.depth += 1;
+        Ok(())
+    }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     type Error = Error;
 
     fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         match self.peek()? {
-            b'i' => self.deserialize_i64(visitor),
+            b'i' => {
+                // Pick the smallest type that holds the integer
+                let text = self.parse_integer()?;
+                if let Ok(n) = text.parse() {
+                    visitor.visit_i64(n)
+                } else if let Ok(n) = text.parse() {
+                    visitor.visit_u64(n)
+                } else if let Ok(n) = text.parse() {
+                    visitor.visit_i128(n)
+                } else if let Ok(n) = text.parse() {
+                    visitor.visit_u128(n)
+                } else {
+                    Err(out_of_range(text, "128-bit integer"))
+                }
+            }
             b'l' => self.deserialize_seq(visitor),
             b'd' => self.deserialize_map(visitor),
             b'0'..=b'9' => self.deserialize_bytes(visitor),
@@ -82,40 +157,48 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     }
 
     fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        let n = self.parse_integer()?;
+        let n: i64 = self.parse_int("bool")?;
         visitor.visit_bool(n != 0)
     }
 
     fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_i8(self.parse_integer()? as i8)
+        visitor.visit_i8(self.parse_int("i8")?)
     }
 
     fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_i16(self.parse_integer()? as i16)
+        visitor.visit_i16(self.parse_int("i16")?)
     }
 
     fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_i32(self.parse_integer()? as i32)
+        visitor.visit_i32(self.parse_int("i32")?)
     }
 
     fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_i64(self.parse_integer()?)
+        visitor.visit_i64(self.parse_int("i64")?)
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i128(self.parse_int("i128")?)
     }
 
     fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_u8(self.parse_integer()? as u8)
+        visitor.visit_u8(self.parse_int("u8")?)
     }
 
     fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_u16(self.parse_integer()? as u16)
+        visitor.visit_u16(self.parse_int("u16")?)
     }
 
     fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_u32(self.parse_integer()? as u32)
+        visitor.visit_u32(self.parse_int("u32")?)
     }
 
     fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_u64(self.parse_integer()? as u64)
+        visitor.visit_u64(self.parse_int("u64")?)
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u128(self.parse_int("u128")?)
     }
 
     fn deserialize_f32<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
@@ -139,8 +222,11 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         let bytes = self.parse_byte_string()?;
-        let s = std::str::from_utf8(bytes).map_err(|_| Error::InvalidCharacter(0))?;
-        visitor.visit_borrowed_str(s)
+        // Let the visitor decide what to do with non-UTF-8 data, as for `Value`
+        match std::str::from_utf8(bytes) {
+            Ok(s) => visitor.visit_borrowed_str(s),
+            Err(_) => visitor.visit_borrowed_bytes(bytes),
+        }
     }
 
     fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
@@ -174,16 +260,21 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     fn deserialize_newtype_struct<V: Visitor<'de>>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value> {
+        if name == raw::TOKEN {
+            return visitor.visit_borrowed_bytes(self.parse_raw()?);
+        }
         visitor.visit_newtype_struct(self)
     }
 
     fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         self.expect(b'l')?;
+        self.enter()?;
         let value = visitor.visit_seq(SeqAccess::new(self))?;
         self.expect(b'e')?;
+        self.depth -= 1;
         Ok(value)
     }
 
@@ -202,8 +293,10 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         self.expect(b'd')?;
+        self.enter()?;
         let value = visitor.visit_map(MapAccess::new(self))?;
         self.expect(b'e')?;
+        self.depth -= 1;
         Ok(value)
     }
 
@@ -220,9 +313,22 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         self,
         _name: &'static str,
         _variants: &'static [&'static str],
-        _visitor: V,
+        visitor: V,
     ) -> Result<V::Value> {
-        Err(Error::Message("bencode does not support enums".into()))
+        match self.peek()? {
+            // Unit variant: just the variant name
+            b'0'..=b'9' => visitor.visit_enum(UnitVariantAccess { de: self }),
+            // Any other variant: a dict with the variant name as its only key
+            b'd' => {
+                self.expect(b'd')?;
+                self.enter()?;
+                let value = visitor.visit_enum(VariantAccess { de: self })?;
+                self.expect(b'e')?;
+                self.depth -= 1;
+                Ok(value)
+            }
+            byte => Err(Error::InvalidCharacter(byte)),
+        }
     }
 
     fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
@@ -236,11 +342,12 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
 struct SeqAccess<'a, 'de> {
     de: &'a mut Deserializer<'de>,
+    index: usize,
 }
 
 impl<'a, 'de> SeqAccess<'a, 'de> {
     fn new(de: &'a mut Deserializer<'de>) -> Self {
-        SeqAccess { de }
+        SeqAccess { de, index: 0 }
     }
 }
 
@@ -255,17 +362,29 @@ impl<'de, 'a> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
         if self.de.peek()? == b'e' {
             return Ok(None);
         }
-        seed.deserialize(&mut *self.de).map(Some)
+        if self.index >= self.de.options.max_items {
+            return Err(self.de.locate(Error::ItemLimitExceeded));
+        }
+        self.de.path.push_index(self.index);
+        let result = seed
+            .deserialize(&mut *self.de)
+            .map_err(|err| self.de.locate(err));
+        self.de.path.pop();
+        self.index += 1;
+        result.map(Some)
     }
 }
 
 struct MapAccess<'a, 'de> {
     de: &'a mut Deserializer<'de>,
+    keys: DictKeys<&'de [u8]>,
+    len: usize,
 }
 
 impl<'a, 'de> MapAccess<'a, 'de> {
     fn new(de: &'a mut Deserializer<'de>) -> Self {
-        MapAccess { de }
+        let keys = DictKeys::new(&de.options);
+        MapAccess { de, keys, len: 0 }
     }
 }
 
@@ -280,14 +399,153 @@ impl<'de, 'a> de::MapAccess<'de> for MapAccess<'a, 'de> {
         if self.de.peek()? == b'e' {
             return Ok(None);
         }
-        seed.deserialize(&mut *self.de).map(Some)
+        if !self.de.peek()?.is_ascii_digit() {
+            return Err(self.de.locate(Error::InvalidDictKey));
+        }
+        if self.len >= self.de.options.max_items {
+            return Err(self.de.locate(Error::ItemLimitExceeded));
+        }
+        self.len += 1;
+
+        // Peek at the raw key so errors in the value can name it
+        let start = self.de.position;
+        let key = self
+            .de
+            .parse_byte_string()
+            .map_err(|err| self.de.locate(err))?;
+        self.keys.check(key).map_err(|err| self.de.locate(err))?;
+        self.de.position = start;
+
+        let key_value = seed
+            .deserialize(&mut *self.de)
+            .map_err(|err| self.de.locate(err))?;
+        self.de.path.push_key(key);
+        Ok(Some(key_value))
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
     where
         V: de::DeserializeSeed<'de>,
     {
-        seed.deserialize(&mut *self.de)
+        let result = seed
+            .deserialize(&mut *self.de)
+            .map_err(|err| self.de.locate(err));
+        self.de.path.pop();
+        result
+    }
+}
+
+struct UnitVariantAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for UnitVariantAccess<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for UnitVariantAccess<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, _seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        Err(de::Error::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"newtype variant",
+        ))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, _visitor: V) -> Result<V::Value> {
+        Err(de::Error::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"tuple variant",
+        ))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value> {
+        Err(de::Error::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"struct variant",
+        ))
+    }
+}
+
+struct VariantAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'a, 'de> VariantAccess<'a, 'de> {
+    /// Deserialize the variant's content, naming the variant in error paths
+    fn content<T>(self, f: impl FnOnce(&mut Deserializer<'de>) -> Result<T>) -> Result<T> {
+        let result = f(&mut *self.de).map_err(|err| self.de.locate(err));
+        self.de.path.pop();
+        result
+    }
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for VariantAccess<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        if !self.de.peek()?.is_ascii_digit() {
+            return Err(Error::InvalidDictKey);
+        }
+        let start = self.de.position;
+        let key = self.de.parse_byte_string()?;
+        self.de.position = start;
+
+        let variant = seed.deserialize(&mut *self.de)?;
+        self.de.path.push_key(key);
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for VariantAccess<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        self.content(|de| de::Deserialize::deserialize(de).map(|_: de::IgnoredAny| ()))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        self.content(|de| seed.deserialize(de))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.content(|de| de::Deserializer::deserialize_seq(de, visitor))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.content(|de| de::Deserializer::deserialize_map(de, visitor))
     }
 }
 
@@ -296,13 +554,26 @@ pub fn from_bytes<'de, T>(input: &'de [u8]) -> Result<T>
 where
     T: Deserialize<'de>,
 {
-    let mut deserializer = Deserializer::new(input);
-    T::deserialize(&mut deserializer)
+    from_bytes_with_options(input, DecoderOptions::default())
+}
+
+/// Deserialize a value from bencode bytes with custom validation rules and limits
+pub fn from_bytes_with_options<'de, T>(input: &'de [u8], options: DecoderOptions) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    let mut deserializer = Deserializer::with_options(input, options);
+    let value = T::deserialize(&mut deserializer).map_err(|err| deserializer.locate(err))?;
+    if options.reject_trailing_data {
+        deserializer.end()?;
+    }
+    Ok(value)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::value::Value;
     use serde::Deserialize;
 
     #[test]
@@ -311,6 +582,56 @@ mod tests {
         assert_eq!(n, 42);
     }
 
+    #[test]
+    fn integer_boundaries() {
+        assert_eq!(from_bytes::<u8>(b"i255e").unwrap(), u8::MAX);
+        assert_eq!(from_bytes::<i8>(b"i-128e").unwrap(), i8::MIN);
+        assert_eq!(
+            from_bytes::<u64>(b"i18446744073709551615e").unwrap(),
+            u64::MAX
+        );
+        assert_eq!(
+            from_bytes::<i128>(b"i-170141183460469231731687303715884105728e").unwrap(),
+            i128::MIN
+        );
+
+        for (input, expected) in [
+            (&b"i256e"[..], "u8"),
+            (b"i-1e", "u32"),
+            (b"i-129e", "i8"),
+            (b"i4294967296e", "u32"),
+            (b"i18446744073709551616e", "u64"),
+            (b"i9223372036854775808e", "i64"),
+        ] {
+            let err = match expected {
+                "u8" => from_bytes::<u8>(input).unwrap_err(),
+                "i8" => from_bytes::<i8>(input).unwrap_err(),
+                "u32" => from_bytes::<u32>(input).unwrap_err(),
+                "u64" => from_bytes::<u64>(input).unwrap_err(),
+                _ => from_bytes::<i64>(input).unwrap_err(),
+            };
+            assert!(
+                matches!(err.inner(), Error::IntegerOutOfRange { expected: e, .. } if *e == expected),
+                "{:?}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn deserialize_any_picks_a_wide_enough_type() {
+        assert_eq!(
+            from_bytes::<Value>(b"i18446744073709551615e").unwrap(),
+            Value::from(u64::MAX)
+        );
+        assert_eq!(
+            from_bytes::<Value>(b"i-9223372036854775809e").unwrap(),
+            Value::from(i64::MIN as i128 - 1)
+        );
+        let err = from_bytes::<Value>(b"i340282366920938463463374607431768211456e").unwrap_err();
+        assert!(matches!(err.inner(), Error::IntegerOutOfRange { .. }));
+    }
+
     #[test]
     fn deserialize_string() {
         let s: String = from_bytes(b"4:spam").unwrap();
@@ -340,4 +661,266 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn error_reports_key_path() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct File {
+            length: i64,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Info {
+            files: Vec<File>,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Torrent {
+            info: Info,
+        }
+
+        let input = b"d4:infod5:filesld6:lengthi1eed6:length3:abceeee";
+        let err = from_bytes::<Torrent>(input).unwrap_err();
+        assert_eq!(err.path(), Some("info.files[1].length"));
+        assert_eq!(err.position(), Some(38));
+
+        let err = from_bytes::<Torrent>(b"d4:infodee").unwrap_err();
+        assert!(matches!(err.inner(), Error::Message(_)));
+        assert_eq!(err.path(), Some("info"));
+    }
+
+    #[test]
+    fn strict_rejects_non_canonical_input() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Pair {
+            a: i64,
+            b: i64,
+        }
+
+        let unsorted = b"d1:bi2e1:ai1ee";
+        let err = from_bytes::<Pair>(unsorted).unwrap_err();
+        assert!(matches!(err.inner(), Error::UnsortedDictKeys));
+        assert!(from_bytes_with_options::<Pair>(unsorted, DecoderOptions::lenient()).is_ok());
+
+        let err = from_bytes::<Pair>(b"d1:ai1e1:bi2eejunk").unwrap_err();
+        assert!(matches!(err.inner(), Error::TrailingData));
+
+        assert!(from_bytes::<i64>(b"i03e").is_err());
+        assert!(from_bytes_with_options::<i64>(b"i03e", DecoderOptions::lenient()).is_ok());
+    }
+
+    #[test]
+    fn enforce_limits() {
+        let options = DecoderOptions {
+            max_depth: 2,
+            max_items: 3,
+            ..DecoderOptions::strict()
+        };
+        assert!(from_bytes_with_options::<Vec<Vec<i64>>>(b"lli1eee", options).is_ok());
+        let err = from_bytes_with_options::<Vec<Vec<Vec<i64>>>>(b"llli1eeee", options).unwrap_err();
+        assert!(matches!(err.inner(), Error::DepthLimitExceeded));
+        let err = from_bytes_with_options::<Vec<i64>>(b"li1ei2ei3ei4ee", options).unwrap_err();
+        assert!(matches!(err.inner(), Error::ItemLimitExceeded));
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Message {
+        Choke,
+        Have(u32),
+        Request(u32, u32, u32),
+        Extended { id: u8, payload: String },
+    }
+
+    #[test]
+    fn deserialize_externally_tagged_enums() {
+        assert_eq!(from_bytes::<Message>(b"5:Choke").unwrap(), Message::Choke);
+        assert_eq!(
+            from_bytes::<Message>(b"d4:Havei7ee").unwrap(),
+            Message::Have(7)
+        );
+        assert_eq!(
+            from_bytes::<Message>(b"d7:Requestli1ei2ei3eee").unwrap(),
+            Message::Request(1, 2, 3)
+        );
+        assert_eq!(
+            from_bytes::<Message>(b"d8:Extendedd2:idi1e7:payload1:xee").unwrap(),
+            Message::Extended {
+                id: 1,
+                payload: "x".into()
+            }
+        );
+    }
+
+    #[test]
+    fn reject_malformed_enums() {
+        assert!(from_bytes::<Message>(b"4:Nope").is_err());
+        assert!(from_bytes::<Message>(b"d4:Havei7e5:Chokelee").is_err());
+        assert!(from_bytes::<Message>(b"d4:Have3:abce").is_err());
+
+        let err = from_bytes::<Message>(b"d8:Extendedd2:idi1e7:payloadi1eee").unwrap_err();
+        assert_eq!(err.path(), Some("Extended.payload"));
+    }
+
+    #[test]
+    fn deserialize_internally_tagged_enum() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        #[serde(tag = "y")]
+        enum Krpc {
+            #[serde(rename = "q")]
+            Query { q: String },
+            #[serde(rename = "e")]
+            Error { e: (i64, String) },
+        }
+
+        assert_eq!(
+            from_bytes::<Krpc>(b"d1:q4:ping1:y1:qe").unwrap(),
+            Krpc::Query { q: "ping".into() }
+        );
+        assert_eq!(
+            from_bytes::<Krpc>(b"d1:eli201e7:Generice1:y1:ee").unwrap(),
+            Krpc::Error {
+                e: (201, "Generic".into())
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_untagged_enum() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        #[serde(untagged)]
+        enum Peers {
+            Compact(#[serde(with = "serde_bytes")] Vec<u8>),
+            List(Vec<Peer>),
+        }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Peer {
+            ip: String,
+            port: u16,
+        }
+
+        assert_eq!(
+            from_bytes::<Peers>(b"6:\x7f\x00\x00\x01\x1a\xe1").unwrap(),
+            Peers::Compact(vec![127, 0, 0, 1, 0x1a, 0xe1])
+        );
+        assert_eq!(
+            from_bytes::<Peers>(b"ld2:ip9:127.0.0.14:porti6881eee").unwrap(),
+            Peers::List(vec![Peer {
+                ip: "127.0.0.1".into(),
+                port: 6881
+            }])
+        );
+    }
+
+    #[test]
+    fn flatten_captures_unknown_keys() {
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Info {
+            name: String,
+            length: u64,
+            #[serde(flatten)]
+            extra: BTreeMap<String, Value>,
+        }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Common {
+            #[serde(rename = "piece length")]
+            piece_length: u32,
+            private: Option<u8>,
+        }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Nested {
+            name: String,
+            #[serde(flatten)]
+            common: Common,
+        }
+
+        let input = b"d6:lengthi42e4:name3:abc12:piece lengthi16384e6:sourcei1ee";
+        let info: Info = from_bytes(input).unwrap();
+        assert_eq!(info.name, "abc");
+        assert_eq!(info.length, 42);
+        assert_eq!(
+            info.extra.keys().collect::<Vec<_>>(),
+            ["piece length", "source"]
+        );
+        assert_eq!(info.extra["source"], Value::Integer(1));
+
+        let nested: Nested = from_bytes(input).unwrap();
+        assert_eq!(
+            nested.common,
+            Common {
+                piece_length: 16384,
+                private: None
+            }
+        );
+    }
+
+    #[test]
+    fn borrow_from_input() {
+        use std::borrow::Cow;
+
+        #[derive(Debug, Deserialize)]
+        struct Announce<'a> {
+            #[serde(rename = "info_hash", with = "serde_bytes")]
+            info_hash: &'a [u8],
+            event: &'a str,
+            #[serde(borrow)]
+            tracker: Cow<'a, str>,
+            peers: Vec<&'a str>,
+        }
+
+        let input = b"d5:event7:started9:info_hash2:\xff\x005:peersl1:a1:be7:tracker3:urle";
+        let announce: Announce = from_bytes(input).unwrap();
+        assert_eq!(announce.info_hash, b"\xff\x00");
+        assert_eq!(announce.event, "started");
+        assert!(matches!(announce.tracker, Cow::Borrowed("url")));
+        assert_eq!(announce.peers, ["a", "b"]);
+
+        // Both point into the input rather than at a copy
+        assert!(input.as_ptr_range().contains(&announce.event.as_ptr()));
+        assert!(input.as_ptr_range().contains(&announce.info_hash.as_ptr()));
+
+        // Non-UTF-8 data cannot borrow as text
+        let err = from_bytes::<&str>(b"1:\xff").unwrap_err();
+        assert!(matches!(err.inner(), Error::Message(_)));
+    }
+
+    #[test]
+    fn missing_fields_and_defaults() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Response {
+            interval: u32,
+            #[serde(rename = "min interval")]
+            min_interval: Option<u32>,
+            #[serde(default)]
+            complete: u32,
+            #[serde(rename = "failure reason")]
+            failure_reason: Option<String>,
+        }
+
+        let response: Response = from_bytes(b"d8:completei5e8:intervali1800ee").unwrap();
+        assert_eq!(
+            response,
+            Response {
+                interval: 1800,
+                min_interval: None,
+                complete: 5,
+                failure_reason: None
+            }
+        );
+
+        let response: Response = from_bytes(b"d8:intervali1800e12:min intervali60ee").unwrap();
+        assert_eq!(response.min_interval, Some(60));
+        assert_eq!(response.complete, 0);
+
+        let err = from_bytes::<Response>(b"de").unwrap_err();
+        assert!(err.to_string().contains("missing field `interval`"));
+    }
 }
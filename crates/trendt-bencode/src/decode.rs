@@ -1,5 +1,10 @@
-use crate::error::{Error, Result};
-use crate::value::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+use crate::error::{Error, KeyPath, Result};
+use crate::integer::{self, Integer, integer_text};
+use crate::options::DecoderOptions;
+use crate::value::{Value, ValueRef};
 
 /// A decoder for bencode data
 pub struct Decoder<'a> {
@@ -7,12 +12,107 @@ pub struct Decoder<'a> {
     input: &'a [u8],
     /// Current position in input
     position: usize,
+    /// Validation rules and limits
+    options: DecoderOptions,
+    /// Current nesting depth of lists and dictionaries
+    depth: usize,
+    /// Path to the value being decoded, for error reporting
+    path: KeyPath<'a>,
+}
+
+/// A value tree the decoder can build, either owned or borrowing from the input
+trait Tree<'a>: Sized {
+    type Key: Ord;
+
+    fn integer(n: Integer) -> Self;
+    fn byte_string(bytes: &'a [u8]) -> Self;
+    fn key(bytes: &'a [u8]) -> Self::Key;
+    fn list(items: Vec<Self>) -> Self;
+    fn dict(map: BTreeMap<Self::Key, Self>) -> Self;
+}
+
+impl<'a> Tree<'a> for Value {
+    type Key = Vec<u8>;
+
+    fn integer(n: Integer) -> Self {
+        match n {
+            Integer::Small(n) => Value::Integer(n),
+            Integer::Big(n) => Value::BigInteger(n),
+        }
+    }
+
+    fn byte_string(bytes: &'a [u8]) -> Self {
+        Value::ByteString(bytes.to_vec())
+    }
+
+    fn key(bytes: &'a [u8]) -> Vec<u8> {
+        bytes.to_vec()
+    }
+
+    fn list(items: Vec<Self>) -> Self {
+        Value::List(items)
+    }
+
+    fn dict(map: BTreeMap<Vec<u8>, Self>) -> Self {
+        Value::Dict(map)
+    }
+}
+
+impl<'a> Tree<'a> for ValueRef<'a> {
+    type Key = &'a [u8];
+
+    fn integer(n: Integer) -> Self {
+        match n {
+            Integer::Small(n) => ValueRef::Integer(n),
+            Integer::Big(n) => ValueRef::BigInteger(n),
+        }
+    }
+
+    fn byte_string(bytes: &'a [u8]) -> Self {
+        ValueRef::ByteString(bytes)
+    }
+
+    fn key(bytes: &'a [u8]) -> &'a [u8] {
+        bytes
+    }
+
+    fn list(items: Vec<Self>) -> Self {
+        ValueRef::List(items)
+    }
+
+    fn dict(map: BTreeMap<&'a [u8], Self>) -> Self {
+        ValueRef::Dict(map)
+    }
 }
 
 impl<'a> Decoder<'a> {
     /// Create a new decoder from input bytes
     pub fn new(input: &'a [u8]) -> Self {
-        Decoder { input, position: 0 }
+        Self::with_options(input, DecoderOptions::default())
+    }
+
+    /// Create a new decoder with custom validation rules and limits
+    pub fn with_options(input: &'a [u8], options: DecoderOptions) -> Self {
+        Decoder {
+            input,
+            position: 0,
+            options,
+            depth: 0,
+            path: KeyPath::default(),
+        }
+    }
+
+    /// Current position in the input
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Check that the whole input has been consumed
+    pub fn end(&self) -> Result<()> {
+        if self.position < self.input.len() {
+            return Err(self.path.locate(Error::TrailingData, self.position));
+        }
+        Ok(())
     }
 
     /// Peek at the current byte without advancing
@@ -32,15 +132,17 @@ impl<'a> Decoder<'a> {
 
     /// Expect a specific byte, error if mismatch
     fn expect(&mut self, expected: u8) -> Result<()> {
-        let byte = self.next()?;
+        // Check before consuming so errors point at the offending byte
+        let byte = self.peek()?;
         if byte != expected {
             return Err(Error::InvalidCharacter(byte));
         }
+        self.next()?;
         Ok(())
     }
 
-    /// Decode an integer: i<number>e
-    fn decode_integer(&mut self) -> Result<i64> {
+    /// Read the digits of an integer after its 'i', stopping at the closing 'e'
+    fn integer_digits(&mut self) -> Result<&'a str> {
         // Expect opening 'i'
         self.expect(b'i')?;
 
@@ -55,27 +157,23 @@ impl<'a> Decoder<'a> {
         }
         let end = self.position;
 
-        // Parse the number
-        let bytes = &self.input[start..end];
-        let string = std::str::from_utf8(bytes).map_err(|_| Error::InvalidInteger)?;
-        let number: i64 = string.parse().map_err(|_| Error::InvalidInteger)?;
+        integer_text(&self.input[start..end], &self.options)
+    }
 
-        // Validate: no leading zeros (except "0" itself), no "-0"
-        if bytes.len() > 1 && bytes[0] == b'0' {
-            return Err(Error::InvalidInteger);
-        }
-        if bytes == b"-0" {
-            return Err(Error::InvalidInteger);
-        }
+    /// Decode an integer: i<number>e
+    fn decode_integer<T: Tree<'a>>(&mut self) -> Result<T> {
+        // Parse the number
+        let text = self.integer_digits()?;
+        let number = integer::parse(text, self.options.big_integers)?;
 
         // Expect closing 'e'
         self.expect(b'e')?;
 
-        Ok(number)
+        Ok(T::integer(number))
     }
 
     /// Decode a byte string: <length>:<data>
-    fn decode_byte_string(&mut self) -> Result<Vec<u8>> {
+    fn decode_byte_string(&mut self) -> Result<&'a [u8]> {
         // Read length digits until ':'
         let start = self.position;
         loop {
@@ -91,19 +189,16 @@ impl<'a> Decoder<'a> {
         let end = self.position;
 
         // Parse length
-        let length_bytes = &self.input[start..end];
-        let length_str =
-            std::str::from_utf8(length_bytes).map_err(|_| Error::InvalidCharacter(0))?;
-        let length: usize = length_str.parse().map_err(|_| Error::InvalidCharacter(0))?;
+        let length = parse_length(&self.input[start..end], &self.options)?;
 
         // Skip ':'
         self.expect(b':')?;
 
         // Read exactly `length` bytes
-        if self.position + length > self.input.len() {
+        if length > self.input.len() - self.position {
             return Err(Error::UnexpectedEof);
         }
-        let data = self.input[self.position..self.position + length].to_vec();
+        let data = &self.input[self.position..self.position + length];
         self.position += length;
 
         Ok(data)
@@ -111,42 +206,228 @@ impl<'a> Decoder<'a> {
 
     /// Decode any bencode value
     pub fn decode_value(&mut self) -> Result<Value> {
+        self.decode_tree()
+    }
+
+    /// Decode any bencode value, borrowing byte strings from the input
+    pub fn decode_value_ref(&mut self) -> Result<ValueRef<'a>> {
+        self.decode_tree()
+    }
+
+    fn decode_tree<T: Tree<'a>>(&mut self) -> Result<T> {
+        self.decode_any()
+            .map_err(|err| self.path.locate(err, self.position))
+    }
+
+    fn decode_any<T: Tree<'a>>(&mut self) -> Result<T> {
         match self.peek()? {
-            b'i' => Ok(Value::Integer(self.decode_integer()?)),
+            b'i' => self.decode_integer(),
             b'l' => self.decode_list(),
             b'd' => self.decode_dict(),
-            b'0'..=b'9' => Ok(Value::ByteString(self.decode_byte_string()?)),
+            b'0'..=b'9' => Ok(T::byte_string(self.decode_byte_string()?)),
             byte => Err(Error::InvalidCharacter(byte)),
         }
     }
 
+    /// Skip over the next value, returning the byte range it occupies
+    ///
+    /// The value is fully validated, so `&input[range]` is exactly the
+    /// original encoding of a well-formed value. Nothing is built along the
+    /// way, so this does not allocate for input with sorted keys.
+    pub fn skip_value(&mut self) -> Result<Range<usize>> {
+        let start = self.position;
+        self.skip_any()
+            .map_err(|err| self.path.locate(err, self.position))?;
+        Ok(start..self.position)
+    }
+
+    fn skip_any(&mut self) -> Result<()> {
+        match self.peek()? {
+            // The integer is not kept, so any size is fine
+            b'i' => {
+                self.integer_digits()?;
+                self.expect(b'e')
+            }
+            b'l' => self.skip_list(),
+            b'd' => self.skip_dict(),
+            b'0'..=b'9' => self.decode_byte_string().map(drop),
+            byte => Err(Error::InvalidCharacter(byte)),
+        }
+    }
+
+    /// Skip a list: l<items>e
+    fn skip_list(&mut self) -> Result<()> {
+        self.expect(b'l')?;
+        self.enter()?;
+
+        let mut len = 0;
+        while self.peek()? != b'e' {
+            if len >= self.options.max_items {
+                return Err(Error::ItemLimitExceeded);
+            }
+            self.path.push_index(len);
+            let item = self.skip_value();
+            self.path.pop();
+            item?;
+            len += 1;
+        }
+
+        self.expect(b'e')?;
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Skip a dictionary: d<pairs>e
+    ///
+    /// Sorted keys can only be duplicates of their predecessor. Only once a
+    /// key arrives out of order (in lenient mode) are the earlier keys
+    /// collected to look for duplicates.
+    fn skip_dict(&mut self) -> Result<()> {
+        self.expect(b'd')?;
+        self.enter()?;
+
+        let entries = self.position;
+        let mut len = 0;
+        let mut last: Option<&'a [u8]> = None;
+        let mut seen: Option<BTreeSet<&'a [u8]>> = None;
+
+        while self.peek()? != b'e' {
+            // Keys must be byte strings
+            if !self.peek()?.is_ascii_digit() {
+                return Err(Error::InvalidDictKey);
+            }
+            if len >= self.options.max_items {
+                return Err(Error::ItemLimitExceeded);
+            }
+            len += 1;
+
+            let key_start = self.position;
+            let key = self.decode_byte_string()?;
+            if let Some(seen) = &mut seen {
+                if !seen.insert(key) {
+                    return Err(Error::DuplicateDictKey);
+                }
+            } else if let Some(prev) = last.filter(|prev| key <= *prev) {
+                if key == prev {
+                    return Err(Error::DuplicateDictKey);
+                }
+                if self.options.canonical {
+                    return Err(Error::UnsortedDictKeys);
+                }
+                let mut keys = self.dict_keys(entries..key_start)?;
+                if !keys.insert(key) {
+                    return Err(Error::DuplicateDictKey);
+                }
+                seen = Some(keys);
+            }
+            last = Some(key);
+
+            self.path.push_key(key);
+            let value = self.skip_value();
+            self.path.pop();
+            value?;
+        }
+
+        self.expect(b'e')?;
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Collect the keys of the already validated dictionary entries in `entries`
+    fn dict_keys(&self, entries: Range<usize>) -> Result<BTreeSet<&'a [u8]>> {
+        let mut decoder = Decoder::with_options(&self.input[..entries.end], self.options);
+        decoder.position = entries.start;
+
+        let mut keys = BTreeSet::new();
+        while decoder.position < entries.end {
+            keys.insert(decoder.decode_byte_string()?);
+            decoder.skip_any()?;
+        }
+        Ok(keys)
+    }
+
+    /// Find the value stored under `key` in the dictionary at the current position
+    ///
+    /// The whole dictionary is consumed and validated. Returns the byte range
+    /// of the entry's value, or `None` if the key is not present.
+    pub fn find_dict_entry(&mut self, key: &[u8]) -> Result<Option<Range<usize>>> {
+        self.find_entry(key)
+            .map_err(|err| self.path.locate(err, self.position))
+    }
+
+    fn find_entry(&mut self, key: &[u8]) -> Result<Option<Range<usize>>> {
+        self.expect(b'd')?;
+        self.enter()?;
+
+        let mut found = None;
+        let mut keys = DictKeys::new(&self.options);
+
+        while self.peek()? != b'e' {
+            if !self.peek()?.is_ascii_digit() {
+                return Err(Error::InvalidDictKey);
+            }
+
+            let entry_key = self.decode_byte_string()?;
+            keys.check(entry_key)?;
+
+            self.path.push_key(entry_key);
+            let span = self.skip_value();
+            self.path.pop();
+            let span = span?;
+            if entry_key == key {
+                found = Some(span);
+            }
+        }
+
+        self.expect(b'e')?;
+        self.depth -= 1;
+
+        Ok(found)
+    }
+
+    /// Track entering a list or dictionary, enforcing the depth limit
+    fn enter(&mut self) -> Result<()> {
+        if self.depth >= self.options.max_depth {
+            return Err(Error::DepthLimitExceeded);
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
     /// Decode a list: l<items>e
-    fn decode_list(&mut self) -> Result<Value> {
+    fn decode_list<T: Tree<'a>>(&mut self) -> Result<T> {
         // Expect opening 'l'
         self.expect(b'l')?;
+        self.enter()?;
 
         let mut items = Vec::new();
 
         // Read items until 'e'
         while self.peek()? != b'e' {
-            items.push(self.decode_value()?);
+            if items.len() >= self.options.max_items {
+                return Err(Error::ItemLimitExceeded);
+            }
+            self.path.push_index(items.len());
+            let item = self.decode_tree();
+            self.path.pop();
+            items.push(item?);
         }
 
         // Expect closing 'e'
         self.expect(b'e')?;
+        self.depth -= 1;
 
-        Ok(Value::List(items))
+        Ok(T::list(items))
     }
 
     /// Decode a dictionary: d<pairs>e
-    fn decode_dict(&mut self) -> Result<Value> {
-        use std::collections::BTreeMap;
-
+    fn decode_dict<T: Tree<'a>>(&mut self) -> Result<T> {
         // Expect opening 'd'
         self.expect(b'd')?;
+        self.enter()?;
 
         let mut map = BTreeMap::new();
-        let mut last_key: Option<Vec<u8>> = None;
+        let mut keys = DictKeys::new(&self.options);
 
         // Read key-value pairs until 'e'
         while self.peek()? != b'e' {
@@ -154,68 +435,176 @@ impl<'a> Decoder<'a> {
             if !self.peek()?.is_ascii_digit() {
                 return Err(Error::InvalidDictKey);
             }
+            if map.len() >= self.options.max_items {
+                return Err(Error::ItemLimitExceeded);
+            }
 
             let key = self.decode_byte_string()?;
-
-            // Keys must be sorted
-            if last_key.as_ref().is_some_and(|prev| key <= *prev) {
-                return Err(Error::UnsortedDictKeys);
-            }
-            last_key = Some(key.clone());
+            keys.check(key)?;
 
             // Decode the value
-            let value = self.decode_value()?;
+            self.path.push_key(key);
+            let value = self.decode_tree();
+            self.path.pop();
 
-            map.insert(key, value);
+            map.insert(T::key(key), value?);
         }
 
         // Expect closing 'e'
         self.expect(b'e')?;
+        self.depth -= 1;
+
+        Ok(T::dict(map))
+    }
+}
+
+/// Parse the length prefix of a byte string (before ':')
+pub(crate) fn parse_length(bytes: &[u8], options: &DecoderOptions) -> Result<usize> {
+    if let Some(&byte) = bytes.iter().find(|byte| !byte.is_ascii_digit()) {
+        return Err(Error::InvalidCharacter(byte));
+    }
+    // Canonical form: no leading zeros (except "0" itself)
+    if options.canonical && bytes.len() > 1 && bytes[0] == b'0' {
+        return Err(Error::InvalidCharacter(b'0'));
+    }
+
+    let string = std::str::from_utf8(bytes).map_err(|_| Error::InvalidCharacter(0))?;
+    let length: usize = string.parse().map_err(|_| Error::InvalidCharacter(0))?;
+    if length > options.max_string_length {
+        return Err(Error::LengthLimitExceeded(length));
+    }
+    Ok(length)
+}
 
-        Ok(Value::Dict(map))
+/// Checks the keys of one dictionary for ordering and uniqueness
+pub(crate) struct DictKeys<K> {
+    canonical: bool,
+    last: Option<K>,
+    seen: BTreeSet<K>,
+}
+
+impl<K: Ord + Clone> DictKeys<K> {
+    pub(crate) fn new(options: &DecoderOptions) -> Self {
+        DictKeys {
+            canonical: options.canonical,
+            last: None,
+            seen: BTreeSet::new(),
+        }
+    }
+
+    /// Check the next key: canonical input must be strictly sorted, and
+    /// duplicates are never allowed
+    pub(crate) fn check(&mut self, key: K) -> Result<()> {
+        if self.canonical {
+            match &self.last {
+                Some(prev) if key == *prev => return Err(Error::DuplicateDictKey),
+                Some(prev) if key < *prev => return Err(Error::UnsortedDictKeys),
+                _ => {}
+            }
+            self.last = Some(key);
+        } else if !self.seen.insert(key) {
+            return Err(Error::DuplicateDictKey);
+        }
+        Ok(())
     }
 }
 
 /// Convenience function to decode bencode bytes to a Value
 pub fn decode(input: &[u8]) -> Result<Value> {
-    let mut decoder = Decoder::new(input);
-    decoder.decode_value()
+    decode_with_options(input, DecoderOptions::default())
+}
+
+/// Decode bencode bytes to a Value with custom validation rules and limits
+pub fn decode_with_options(input: &[u8], options: DecoderOptions) -> Result<Value> {
+    let mut decoder = Decoder::with_options(input, options);
+    let value = decoder.decode_value()?;
+    if options.reject_trailing_data {
+        decoder.end()?;
+    }
+    Ok(value)
+}
+
+/// Convenience function to decode bencode bytes to a ValueRef borrowing from `input`
+pub fn decode_ref(input: &[u8]) -> Result<ValueRef<'_>> {
+    let options = DecoderOptions::default();
+    let mut decoder = Decoder::with_options(input, options);
+    let value = decoder.decode_value_ref()?;
+    if options.reject_trailing_data {
+        decoder.end()?;
+    }
+    Ok(value)
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::BTreeMap;
-
     use super::*;
 
     #[test]
     fn decode_positive_integer() {
         let mut decoder = Decoder::new(b"i42e");
-        assert_eq!(decoder.decode_integer().unwrap(), 42);
+        assert_eq!(
+            decoder.decode_integer::<ValueRef>().unwrap(),
+            ValueRef::Integer(42)
+        );
     }
 
     #[test]
     fn decode_negative_integer() {
         let mut decoder = Decoder::new(b"i-3e");
-        assert_eq!(decoder.decode_integer().unwrap(), -3);
+        assert_eq!(
+            decoder.decode_integer::<ValueRef>().unwrap(),
+            ValueRef::Integer(-3)
+        );
+    }
+
+    #[test]
+    fn integer_range() {
+        assert_eq!(
+            decode(b"i-9223372036854775808e").unwrap(),
+            Value::Integer(i64::MIN)
+        );
+        let err = decode(b"i9223372036854775808e").unwrap_err();
+        assert!(matches!(
+            err.inner(),
+            Error::IntegerOutOfRange {
+                expected: "i64",
+                ..
+            }
+        ));
+
+        let options = DecoderOptions {
+            big_integers: true,
+            ..DecoderOptions::strict()
+        };
+        let value = decode_with_options(b"i9223372036854775808e", options).unwrap();
+        assert_eq!(value, Value::from(i64::MAX as u64 + 1));
+        let value = decode_with_options(b"i-9223372036854775808e", options).unwrap();
+        assert_eq!(value, Value::Integer(i64::MIN));
+
+        // Raw capture only validates, so any size is fine
+        let mut decoder = Decoder::new(b"li99999999999999999999999ee");
+        assert_eq!(decoder.skip_value().unwrap(), 0..27);
     }
 
     #[test]
     fn decode_zero() {
         let mut decoder = Decoder::new(b"i0e");
-        assert_eq!(decoder.decode_integer().unwrap(), 0);
+        assert_eq!(
+            decoder.decode_integer::<ValueRef>().unwrap(),
+            ValueRef::Integer(0)
+        );
     }
 
     #[test]
     fn reject_leading_zero() {
         let mut decoder = Decoder::new(b"i03e");
-        assert!(decoder.decode_integer().is_err());
+        assert!(decoder.decode_integer::<ValueRef>().is_err());
     }
 
     #[test]
     fn reject_negative_zero() {
         let mut decoder = Decoder::new(b"i-0e");
-        assert!(decoder.decode_integer().is_err());
+        assert!(decoder.decode_integer::<ValueRef>().is_err());
     }
 
     #[test]
@@ -302,4 +691,167 @@ mod tests {
         let mut decoder = Decoder::new(b"d3:fooi1e3:bari2ee");
         assert!(decoder.decode_value().is_err());
     }
+
+    #[test]
+    fn skip_value_returns_span() {
+        let input = b"li1e4:spamd3:fooi2eee";
+        let mut decoder = Decoder::new(input);
+        decoder.expect(b'l').unwrap();
+        assert_eq!(decoder.skip_value().unwrap(), 1..4);
+        assert_eq!(decoder.skip_value().unwrap(), 4..10);
+        assert_eq!(decoder.skip_value().unwrap(), 10..20);
+        assert_eq!(&input[10..20], b"d3:fooi2ee");
+    }
+
+    #[test]
+    fn skip_value_validates() {
+        let lenient = DecoderOptions::lenient();
+        let skip = |input: &[u8], options| Decoder::with_options(input, options).skip_value();
+
+        assert_eq!(skip(b"d1:bi1e1:ai2e1:cle0:i3ee", lenient).unwrap(), 0..24);
+        let err = skip(b"d1:bi1e1:ai2e1:bi3ee", lenient).unwrap_err();
+        assert!(matches!(err.inner(), Error::DuplicateDictKey));
+        let err = skip(b"d1:ai1e1:ai2ee", lenient).unwrap_err();
+        assert!(matches!(err.inner(), Error::DuplicateDictKey));
+
+        let err = skip(b"d1:bi1e1:ai2ee", DecoderOptions::strict()).unwrap_err();
+        assert!(matches!(err.inner(), Error::UnsortedDictKeys));
+        let err = skip(b"di1ei2ee", lenient).unwrap_err();
+        assert!(matches!(err.inner(), Error::InvalidDictKey));
+        let err = skip(b"ld1:ai1-eee", lenient).unwrap_err();
+        assert!(matches!(err.inner(), Error::InvalidInteger));
+        assert_eq!(err.path(), Some("[0].a"));
+    }
+
+    #[test]
+    fn find_dict_entry_span() {
+        let input = b"d8:announce3:url4:infod6:lengthi42e4:name3:abce3:zzzi0ee";
+        let span = Decoder::new(input)
+            .find_dict_entry(b"info")
+            .unwrap()
+            .unwrap();
+        assert_eq!(&input[span], b"d6:lengthi42e4:name3:abce");
+    }
+
+    #[test]
+    fn find_dict_entry_missing_key() {
+        let mut decoder = Decoder::new(b"d3:fooi1ee");
+        assert_eq!(decoder.find_dict_entry(b"info").unwrap(), None);
+    }
+
+    #[test]
+    fn find_dict_entry_validates_whole_dict() {
+        let mut decoder = Decoder::new(b"d4:infoi1e3:abci2ee");
+        assert!(decoder.find_dict_entry(b"info").is_err());
+    }
+
+    #[test]
+    fn decode_ref_borrows_input() {
+        let input = b"d3:bar4:spam3:fooli1eee";
+        let value = decode_ref(input).unwrap();
+        let ValueRef::Dict(map) = &value else {
+            panic!("expected dict");
+        };
+        let Some(ValueRef::ByteString(bar)) = map.get(&b"bar"[..]) else {
+            panic!("expected byte string");
+        };
+        assert_eq!(bar.as_ptr(), input[8..].as_ptr());
+        assert_eq!(Value::from(value), decode(input).unwrap());
+    }
+
+    #[test]
+    fn error_reports_position_and_path() {
+        let input = b"d4:infod5:filesld6:lengthi1eed6:lengthi01eeeee";
+        let err = decode(input).unwrap_err();
+        assert!(matches!(err.inner(), Error::InvalidInteger));
+        assert_eq!(err.path(), Some("info.files[1].length"));
+        assert_eq!(err.position(), Some(41));
+    }
+
+    #[test]
+    fn error_at_top_level_has_empty_path() {
+        let err = decode(b"x").unwrap_err();
+        assert!(matches!(err.inner(), Error::InvalidCharacter(b'x')));
+        assert_eq!(err.path(), Some(""));
+        assert_eq!(err.position(), Some(0));
+    }
+
+    #[test]
+    fn reject_trailing_data() {
+        let err = decode(b"i1ei2e").unwrap_err();
+        assert!(matches!(err.inner(), Error::TrailingData));
+        assert_eq!(err.position(), Some(3));
+    }
+
+    #[test]
+    fn lenient_allows_trailing_data() {
+        let value = decode_with_options(b"i1e\n", DecoderOptions::lenient()).unwrap();
+        assert_eq!(value, Value::Integer(1));
+    }
+
+    #[test]
+    fn lenient_allows_non_canonical_input() {
+        let options = DecoderOptions::lenient();
+        assert!(decode_with_options(b"d3:fooi1e3:bari2ee", options).is_ok());
+        assert!(decode_with_options(b"i03e", options).is_ok());
+        assert!(decode_with_options(b"i-0e", options).is_ok());
+        assert!(decode_with_options(b"03:abc", options).is_ok());
+        assert!(decode(b"03:abc").is_err());
+    }
+
+    #[test]
+    fn reject_duplicate_keys() {
+        for options in [DecoderOptions::strict(), DecoderOptions::lenient()] {
+            let err = decode_with_options(b"d3:bari2e3:fooi1e3:fooi3ee", options).unwrap_err();
+            assert!(matches!(err.inner(), Error::DuplicateDictKey));
+        }
+    }
+
+    #[test]
+    fn reject_malformed_integers() {
+        for input in [&b"i-03e"[..], b"i+3e", b"ie", b"i-e", b"i1-e", b"i 1e"] {
+            assert!(decode(input).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn enforce_depth_limit() {
+        let options = DecoderOptions {
+            max_depth: 3,
+            ..DecoderOptions::strict()
+        };
+        assert!(decode_with_options(b"llleee", options).is_ok());
+        let err = decode_with_options(b"lllleeee", options).unwrap_err();
+        assert!(matches!(err.inner(), Error::DepthLimitExceeded));
+
+        // The default limit keeps deeply nested input off the stack
+        let mut input = vec![b'l'; 100_000];
+        input.extend(vec![b'e'; 100_000]);
+        assert!(decode(&input).is_err());
+    }
+
+    #[test]
+    fn enforce_string_length_limit() {
+        let options = DecoderOptions {
+            max_string_length: 4,
+            ..DecoderOptions::strict()
+        };
+        assert!(decode_with_options(b"4:spam", options).is_ok());
+        let err = decode_with_options(b"5:spams", options).unwrap_err();
+        assert!(matches!(err.inner(), Error::LengthLimitExceeded(5)));
+
+        // Lengths beyond the input are rejected without overflowing
+        assert!(decode(b"18446744073709551615:x").is_err());
+    }
+
+    #[test]
+    fn enforce_item_limit() {
+        let options = DecoderOptions {
+            max_items: 2,
+            ..DecoderOptions::strict()
+        };
+        assert!(decode_with_options(b"li1ei2ee", options).is_ok());
+        assert!(decode_with_options(b"li1ei2ei3ee", options).is_err());
+        assert!(decode_with_options(b"d1:ai1e1:bi2e1:ci3ee", options).is_err());
+    }
 }
@@ -1,55 +1,67 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use crate::error::Result;
 use crate::value::Value;
 
 /// Encode a bencode value to bytes
 pub fn encode(value: &Value) -> Vec<u8> {
     let mut output = Vec::new();
-    encode_value(value, &mut output);
+    encode_value(value, &mut output).expect("writing to a Vec cannot fail");
     output
 }
 
-fn encode_value(value: &Value, output: &mut Vec<u8>) {
+/// Encode a bencode value directly into a writer
+///
+/// Every token is a separate write, so wrap unbuffered writers such as files
+/// and sockets in a [`BufWriter`](std::io::BufWriter).
+pub fn encode_to_writer<W: Write>(value: &Value, mut writer: W) -> Result<()> {
+    encode_value(value, &mut writer)?;
+    Ok(())
+}
+
+fn encode_value<W: Write>(value: &Value, output: &mut W) -> io::Result<()> {
     match value {
-        Value::Integer(n) => encode_integer(*n, output),
+        Value::Integer(n) => encode_integer(n, output),
+        Value::BigInteger(n) => encode_integer(n, output),
         Value::ByteString(bytes) => encode_byte_string(bytes, output),
         Value::List(items) => encode_list(items, output),
         Value::Dict(map) => encode_dict(map, output),
     }
 }
 
-fn encode_integer(n: i64, output: &mut Vec<u8>) {
-    output.push(b'i');
-    output.extend(n.to_string().as_bytes());
-    output.push(b'e');
+fn encode_integer<W: Write>(n: impl Display, output: &mut W) -> io::Result<()> {
+    write!(output, "i{}e", n)
 }
 
-fn encode_byte_string(bytes: &[u8], output: &mut Vec<u8>) {
-    output.extend(bytes.len().to_string().as_bytes());
-    output.push(b':');
-    output.extend(bytes);
+fn encode_byte_string<W: Write>(bytes: &[u8], output: &mut W) -> io::Result<()> {
+    write!(output, "{}:", bytes.len())?;
+    output.write_all(bytes)
 }
 
-fn encode_list(items: &[Value], output: &mut Vec<u8>) {
-    output.push(b'l');
+fn encode_list<W: Write>(items: &[Value], output: &mut W) -> io::Result<()> {
+    output.write_all(b"l")?;
     for item in items {
-        encode_value(item, output);
+        encode_value(item, output)?;
     }
-    output.push(b'e');
+    output.write_all(b"e")
 }
 
-fn encode_dict(map: &std::collections::BTreeMap<Vec<u8>, Value>, output: &mut Vec<u8>) {
-    output.push(b'd');
+fn encode_dict<W: Write>(map: &BTreeMap<Vec<u8>, Value>, output: &mut W) -> io::Result<()> {
+    output.write_all(b"d")?;
     // BTreeMap iterates in sorted order, so keys are already sorted
     for (key, value) in map {
-        encode_byte_string(key, output);
-        encode_value(value, output);
+        encode_byte_string(key, output)?;
+        encode_value(value, output)?;
     }
-    output.push(b'e');
+    output.write_all(b"e")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::BTreeMap;
+    use crate::error::Error;
 
     #[test]
     fn encode_integer() {
@@ -97,4 +109,22 @@ mod tests {
         let encoded = encode(&value);
         assert_eq!(encoded, original);
     }
+
+    #[test]
+    fn encode_to_writer_matches_encode() {
+        let mut map = BTreeMap::new();
+        map.insert(b"foo".to_vec(), Value::List(vec![Value::Integer(1)]));
+        let value = Value::Dict(map);
+
+        let mut output = Vec::new();
+        encode_to_writer(&value, &mut output).unwrap();
+        assert_eq!(output, encode(&value));
+    }
+
+    #[test]
+    fn encode_to_writer_reports_io_errors() {
+        let mut buffer = [0u8; 4];
+        let result = encode_to_writer(&Value::ByteString(b"spam".to_vec()), &mut buffer[..]);
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
 }
@@ -1,4 +1,5 @@
-use std::fmt;
+use std::borrow::Cow;
+use std::{fmt, io};
 
 use serde::{de, ser};
 
@@ -10,10 +11,40 @@ pub enum Error {
     InvalidCharacter(u8),
     /// Invalid integer format
     InvalidInteger,
+    /// Integer does not fit the type it is decoded into
+    IntegerOutOfRange {
+        /// The integer's decimal digits
+        value: String,
+        /// The type it was decoded into, e.g. `u8`
+        expected: &'static str,
+    },
     /// Dictionary keys must be byte strings
     InvalidDictKey,
     /// Dictionary keys must be sorted
     UnsortedDictKeys,
+    /// Dictionary key appears more than once
+    DuplicateDictKey,
+    /// Input continues after the top-level value
+    TrailingData,
+    /// Lists and dictionaries are nested too deeply
+    DepthLimitExceeded,
+    /// Byte string longer than allowed
+    LengthLimitExceeded(usize),
+    /// List or dictionary has too many items
+    ItemLimitExceeded,
+    /// Incremental input buffered for one value grew past the limit
+    BufferLimitExceeded(usize),
+    /// Underlying reader or writer failed
+    Io(io::Error),
+    /// Another error, annotated with where in the input it occurred
+    At {
+        /// Byte offset into the input
+        position: usize,
+        /// Dictionary keys and list indices leading to the value, e.g. `info.files[3].length`
+        path: String,
+        /// The underlying error
+        source: Box<Error>,
+    },
 
     /// Custom message from serde
     Message(String),
@@ -25,15 +56,96 @@ impl fmt::Display for Error {
             Error::UnexpectedEof => write!(f, "unexpected end of input"),
             Error::InvalidCharacter(c) => write!(f, "invalid character: {}", *c as char),
             Error::InvalidInteger => write!(f, "invalid integer format"),
+            Error::IntegerOutOfRange { value, expected } => {
+                write!(f, "integer {} is out of range for {}", value, expected)
+            }
             Error::InvalidDictKey => write!(f, "dictionary keys must be byte strings"),
             Error::UnsortedDictKeys => write!(f, "dictionary keys must be sorted"),
+            Error::DuplicateDictKey => write!(f, "duplicate dictionary key"),
+            Error::TrailingData => write!(f, "trailing data after value"),
+            Error::DepthLimitExceeded => write!(f, "maximum nesting depth exceeded"),
+            Error::LengthLimitExceeded(len) => {
+                write!(f, "byte string of {} bytes exceeds length limit", len)
+            }
+            Error::ItemLimitExceeded => write!(f, "too many items in list or dictionary"),
+            Error::BufferLimitExceeded(len) => {
+                write!(f, "value of {} bytes exceeds buffer limit", len)
+            }
+            Error::Io(err) => write!(f, "i/o error: {}", err),
+            Error::At {
+                position,
+                path,
+                source,
+            } => {
+                write!(f, "{} at byte {}", source, position)?;
+                if !path.is_empty() {
+                    write!(f, " in {}", path)?;
+                }
+                Ok(())
+            }
 
             Error::Message(msg) => write!(f, "{}", msg),
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl Error {
+    /// Byte offset into the input where the error occurred, if known
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            Error::At { position, .. } => Some(*position),
+            _ => None,
+        }
+    }
+
+    /// Key path to the value that failed, if known (empty for the top-level value)
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Error::At { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Move an error located within a sub-slice into the coordinates of the whole input
+    pub(crate) fn offset(self, base: usize, prefix: &KeyPath<'_>) -> Error {
+        match self {
+            Error::At {
+                position,
+                path,
+                source,
+            } => Error::At {
+                position: base + position,
+                path: prefix.join(&path),
+                source,
+            },
+            err => prefix.locate(err, base),
+        }
+    }
+
+    /// The underlying error, without position information
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::At { source, .. } => source.inner(),
+            err => err,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::At { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
 
 impl de::Error for Error {
     fn custom<T: std::fmt::Display>(msg: T) -> Self {
@@ -48,3 +160,104 @@ impl ser::Error for Error {
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+/// One step on the way from the top-level value to a nested one
+enum Segment<'a> {
+    Key(Cow<'a, [u8]>),
+    Index(usize),
+}
+
+/// Tracks the current dictionary-key/list-index path while decoding
+///
+/// Keys borrow from the input where possible; the path is only formatted
+/// once an error is actually reported.
+#[derive(Default)]
+pub(crate) struct KeyPath<'a> {
+    segments: Vec<Segment<'a>>,
+}
+
+impl<'a> KeyPath<'a> {
+    pub(crate) fn push_key(&mut self, key: impl Into<Cow<'a, [u8]>>) {
+        self.segments.push(Segment::Key(key.into()));
+    }
+
+    pub(crate) fn push_index(&mut self, index: usize) {
+        self.segments.push(Segment::Index(index));
+    }
+
+    pub(crate) fn pop(&mut self) {
+        self.segments.pop();
+    }
+
+    /// Remove the last segment, handing back its key if it was one
+    pub(crate) fn pop_key(&mut self) -> Option<Cow<'a, [u8]>> {
+        match self.segments.pop()? {
+            Segment::Key(key) => Some(key),
+            Segment::Index(_) => None,
+        }
+    }
+
+    /// Append a formatted sub-path to this path
+    pub(crate) fn join(&self, suffix: &str) -> String {
+        let path = self.to_string();
+        if path.is_empty() || suffix.is_empty() || suffix.starts_with('[') {
+            path + suffix
+        } else {
+            format!("{}.{}", path, suffix)
+        }
+    }
+
+    /// Annotate `err` with the current path, unless a deeper value already did
+    pub(crate) fn locate(&self, err: Error, position: usize) -> Error {
+        if let Error::At { .. } = err {
+            return err;
+        }
+        Error::At {
+            position,
+            path: self.to_string(),
+            source: Box::new(err),
+        }
+    }
+}
+
+impl fmt::Display for KeyPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Key(key) if i == 0 => write!(f, "{}", String::from_utf8_lossy(key))?,
+                Segment::Key(key) => write!(f, ".{}", String::from_utf8_lossy(key))?,
+                Segment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_key_path() {
+        let mut path = KeyPath::default();
+        path.push_key(b"info");
+        path.push_key(b"files");
+        path.push_index(3);
+        path.push_key(b"length");
+        assert_eq!(path.to_string(), "info.files[3].length");
+    }
+
+    #[test]
+    fn locate_keeps_innermost_position() {
+        let mut path = KeyPath::default();
+        path.push_index(1);
+        let err = path.locate(Error::InvalidInteger, 7);
+        path.pop();
+        let err = path.locate(err, 9);
+
+        assert_eq!(err.position(), Some(7));
+        assert_eq!(err.path(), Some("[1]"));
+        assert!(matches!(err.inner(), Error::InvalidInteger));
+        assert_eq!(err.to_string(), "invalid integer format at byte 7 in [1]");
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::options::DecoderOptions;
+
+/// An integer outside the range of `i64`, kept as canonical decimal text
+///
+/// Bencode puts no bound on integers. Decoders only produce these when
+/// [`DecoderOptions::big_integers`] is set; integers that fit in `i64` are
+/// always [`Value::Integer`](crate::Value::Integer), so each number has one
+/// representation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInteger(String);
+
+impl BigInteger {
+    /// The decimal digits, with a leading `-` if negative
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether the integer is below zero
+    pub fn is_negative(&self) -> bool {
+        self.0.starts_with('-')
+    }
+
+    /// Wrap validated digits that do not fit in `i64`
+    fn from_text(text: &str) -> Self {
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text),
+        };
+        BigInteger(format!("{}{}", sign, digits.trim_start_matches('0')))
+    }
+}
+
+impl fmt::Display for BigInteger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for BigInteger {
+    type Err = Error;
+
+    /// Parse decimal digits with an optional `-`; fails if the value fits in `i64`
+    fn from_str(s: &str) -> Result<Self> {
+        let text = integer_text(s.as_bytes(), &DecoderOptions::lenient())?;
+        match parse(text, true)? {
+            Integer::Small(_) => Err(Error::Message(format!("integer {} fits in i64", s))),
+            Integer::Big(n) => Ok(n),
+        }
+    }
+}
+
+macro_rules! try_from_big {
+    ($($ty:ty)*) => {
+        $(
+            impl TryFrom<&BigInteger> for $ty {
+                type Error = Error;
+
+                fn try_from(n: &BigInteger) -> Result<$ty> {
+                    n.0.parse()
+                        .map_err(|_| out_of_range(&n.0, stringify!($ty)))
+                }
+            }
+        )*
+    };
+}
+
+try_from_big!(u64 i128 u128);
+
+/// A decoded integer of any size
+pub(crate) enum Integer {
+    Small(i64),
+    Big(BigInteger),
+}
+
+/// Validate the digits of an integer (between `i` and `e`) and return them as text
+///
+/// Negative zero, which lenient mode accepts, comes back without its sign.
+pub(crate) fn integer_text<'a>(bytes: &'a [u8], options: &DecoderOptions) -> Result<&'a str> {
+    // Only an optional '-' followed by decimal digits is valid
+    let digits = bytes.strip_prefix(b"-").unwrap_or(bytes);
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return Err(Error::InvalidInteger);
+    }
+
+    // Canonical form: no leading zeros (except "0" itself), no "-0"
+    if options.canonical && ((digits.len() > 1 && digits[0] == b'0') || bytes == b"-0") {
+        return Err(Error::InvalidInteger);
+    }
+
+    let bytes = if digits.iter().all(|&digit| digit == b'0') {
+        digits
+    } else {
+        bytes
+    };
+    std::str::from_utf8(bytes).map_err(|_| Error::InvalidInteger)
+}
+
+/// Parse validated digits, falling back to [`BigInteger`] if allowed
+pub(crate) fn parse(text: &str, big_integers: bool) -> Result<Integer> {
+    match text.parse() {
+        Ok(n) => Ok(Integer::Small(n)),
+        Err(_) if big_integers => Ok(Integer::Big(BigInteger::from_text(text))),
+        Err(_) => Err(out_of_range(text, "i64")),
+    }
+}
+
+/// The error for an integer that does not fit the requested type
+pub(crate) fn out_of_range(text: &str, expected: &'static str) -> Error {
+    Error::IntegerOutOfRange {
+        value: text.to_owned(),
+        expected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn big_integers_are_canonical() {
+        let n: BigInteger = "-0009223372036854775809".parse().unwrap();
+        assert_eq!(n.as_str(), "-9223372036854775809");
+        assert!(n.is_negative());
+        assert!("9223372036854775807".parse::<BigInteger>().is_err());
+        assert!("12a".parse::<BigInteger>().is_err());
+    }
+
+    #[test]
+    fn checked_conversions() {
+        let n: BigInteger = "18446744073709551615".parse().unwrap();
+        assert_eq!(u64::try_from(&n).unwrap(), u64::MAX);
+        assert_eq!(i128::try_from(&n).unwrap(), u64::MAX as i128);
+
+        let n: BigInteger = "-18446744073709551615".parse().unwrap();
+        let err = u64::try_from(&n).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::IntegerOutOfRange {
+                expected: "u64",
+                ..
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "integer -18446744073709551615 is out of range for u64"
+        );
+    }
+
+    #[test]
+    fn negative_zero_loses_its_sign() {
+        let options = DecoderOptions::lenient();
+        assert_eq!(integer_text(b"-0", &options).unwrap(), "0");
+        assert_eq!(integer_text(b"-000", &options).unwrap(), "000");
+        assert_eq!(integer_text(b"-010", &options).unwrap(), "-010");
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde_json::{Map, Value as Json};
+
+use crate::error::{Error, Result};
+use crate::integer::{self, Integer, integer_text};
+use crate::options::DecoderOptions;
+use crate::pretty::hex;
+use crate::value::Value;
+
+/// Tag for a binary string written as hex
+const HEX: &str = "$hex";
+/// Tag for a binary string written as base64
+const BASE64: &str = "$base64";
+/// Tag for a dictionary written as a list of `[key, value]` pairs
+const DICT: &str = "$dict";
+/// Tag for an integer too large for a JSON number, written as decimal text
+const INT: &str = "$int";
+
+/// How byte strings that are not valid UTF-8 are written to JSON
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BinaryEncoding {
+    /// `{"$hex": "0a1b"}`
+    #[default]
+    Hex,
+    /// `{"$base64": "Chs="}`
+    Base64,
+}
+
+/// Convert a bencode value to JSON, writing binary strings as hex
+///
+/// UTF-8 byte strings become JSON strings and everything else uses a tagged
+/// object, so [`from_json`] can always rebuild the original value:
+///
+/// - binary strings become `{"$hex": "..."}` (or `{"$base64": "..."}`)
+/// - integers outside the `i64` range become `{"$int": "..."}`
+/// - dictionaries with a non-UTF-8 key, or whose only key is one of these
+///   tags, become `{"$dict": [[key, value], ...]}`
+pub fn to_json(value: &Value) -> Json {
+    to_json_with(value, BinaryEncoding::default())
+}
+
+/// Convert a bencode value to JSON with the given binary encoding
+pub fn to_json_with(value: &Value, binary: BinaryEncoding) -> Json {
+    match value {
+        Value::Integer(n) => Json::from(*n),
+        Value::BigInteger(n) => tagged(INT, Json::String(n.to_string())),
+        Value::ByteString(bytes) => bytes_to_json(bytes, binary),
+        Value::List(items) => Json::Array(
+            items
+                .iter()
+                .map(|item| to_json_with(item, binary))
+                .collect(),
+        ),
+        Value::Dict(map) => dict_to_json(map, binary),
+    }
+}
+
+fn bytes_to_json(bytes: &[u8], binary: BinaryEncoding) -> Json {
+    if let Ok(s) = std::str::from_utf8(bytes) {
+        return Json::String(s.to_owned());
+    }
+    let (tag, encoded) = match binary {
+        BinaryEncoding::Hex => (HEX, hex(bytes)),
+        BinaryEncoding::Base64 => (BASE64, STANDARD.encode(bytes)),
+    };
+    tagged(tag, Json::String(encoded))
+}
+
+fn dict_to_json(map: &BTreeMap<Vec<u8>, Value>, binary: BinaryEncoding) -> Json {
+    let plain = map.keys().all(|key| std::str::from_utf8(key).is_ok())
+        && !(map.len() == 1 && map.keys().any(|key| is_tag(key)));
+    if plain {
+        let object = map
+            .iter()
+            .map(|(key, value)| {
+                let key = String::from_utf8(key.clone()).expect("keys checked above");
+                (key, to_json_with(value, binary))
+            })
+            .collect();
+        return Json::Object(object);
+    }
+
+    let pairs = map
+        .iter()
+        .map(|(key, value)| {
+            Json::Array(vec![
+                bytes_to_json(key, binary),
+                to_json_with(value, binary),
+            ])
+        })
+        .collect();
+    tagged(DICT, Json::Array(pairs))
+}
+
+fn is_tag(key: &[u8]) -> bool {
+    [HEX, BASE64, DICT, INT]
+        .iter()
+        .any(|tag| tag.as_bytes() == key)
+}
+
+fn tagged(tag: &str, value: Json) -> Json {
+    let mut object = Map::new();
+    object.insert(tag.to_owned(), value);
+    Json::Object(object)
+}
+
+/// Convert JSON produced by [`to_json`] back into a bencode value
+///
+/// Plain JSON is accepted too, as long as it only holds integers, strings,
+/// arrays and objects: bencode has no floats, booleans or null.
+pub fn from_json(json: &Json) -> Result<Value> {
+    match json {
+        Json::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(n), _) => Ok(Value::Integer(n)),
+            (None, Some(n)) => Ok(Value::from(n)),
+            _ => Err(Error::Message(format!("number {} is not an integer", n))),
+        },
+        Json::String(s) => Ok(Value::ByteString(s.as_bytes().to_vec())),
+        Json::Array(items) => items
+            .iter()
+            .map(from_json)
+            .collect::<Result<_>>()
+            .map(Value::List),
+        Json::Object(object) => object_from_json(object),
+        Json::Bool(_) => Err(Error::Message("bencode has no booleans".into())),
+        Json::Null => Err(Error::Message("bencode has no null".into())),
+    }
+}
+
+fn object_from_json(object: &Map<String, Json>) -> Result<Value> {
+    if let Some((tag, content)) = object.iter().next().filter(|_| object.len() == 1) {
+        match tag.as_str() {
+            HEX => return unhex(tag_content(content)?).map(Value::ByteString),
+            BASE64 => {
+                return STANDARD
+                    .decode(tag_content(content)?)
+                    .map(Value::ByteString)
+                    .map_err(|err| Error::Message(format!("invalid base64 string: {}", err)));
+            }
+            DICT => return pairs_from_json(content),
+            INT => {
+                let text =
+                    integer_text(tag_content(content)?.as_bytes(), &DecoderOptions::strict())?;
+                return match integer::parse(text, true)? {
+                    Integer::Small(n) => Ok(Value::Integer(n)),
+                    Integer::Big(n) => Ok(Value::BigInteger(n)),
+                };
+            }
+            _ => {}
+        }
+    }
+
+    let mut map = BTreeMap::new();
+    for (key, value) in object {
+        map.insert(key.as_bytes().to_vec(), from_json(value)?);
+    }
+    Ok(Value::Dict(map))
+}
+
+/// The encoded string inside a `$hex`, `$base64` or `$int` tag
+fn tag_content(json: &Json) -> Result<&str> {
+    json.as_str()
+        .ok_or_else(|| Error::Message("expected an encoded string".into()))
+}
+
+fn pairs_from_json(json: &Json) -> Result<Value> {
+    let Json::Array(pairs) = json else {
+        return Err(Error::Message(
+            "expected an array of [key, value] pairs".into(),
+        ));
+    };
+
+    let mut map = BTreeMap::new();
+    for pair in pairs {
+        let [key, value] = pair.as_array().map(Vec::as_slice).unwrap_or_default() else {
+            return Err(Error::Message("expected a [key, value] pair".into()));
+        };
+        let Value::ByteString(key) = from_json(key)? else {
+            return Err(Error::InvalidDictKey);
+        };
+        if map.insert(key, from_json(value)?).is_some() {
+            return Err(Error::DuplicateDictKey);
+        }
+    }
+    Ok(Value::Dict(map))
+}
+
+fn unhex(s: &str) -> Result<Vec<u8>> {
+    let invalid = || Error::Message(format!("invalid hex string {:?}", s));
+    if !s.len().is_multiple_of(2) || !s.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| invalid()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::decode::decode;
+
+    #[test]
+    fn utf8_strings_stay_readable() {
+        let value = decode(b"d8:announce3:url4:infod6:lengthi42e4:name3:abcee").unwrap();
+        assert_eq!(
+            to_json(&value),
+            json!({"announce": "url", "info": {"length": 42, "name": "abc"}})
+        );
+    }
+
+    #[test]
+    fn binary_strings_are_tagged() {
+        let value = Value::ByteString(vec![0xff, 0x00, 0x1b]);
+        assert_eq!(to_json(&value), json!({"$hex": "ff001b"}));
+        assert_eq!(
+            to_json_with(&value, BinaryEncoding::Base64),
+            json!({"$base64": "/wAb"})
+        );
+    }
+
+    #[test]
+    fn awkward_dicts_use_pairs() {
+        let value = decode(b"d2:\xff\x00i1ee").unwrap();
+        assert_eq!(to_json(&value), json!({"$dict": [[{"$hex": "ff00"}, 1]]}));
+
+        // A real dict that looks like a tag must not be read back as one
+        let value = decode(b"d4:$hex2:abe").unwrap();
+        assert_eq!(to_json(&value), json!({"$dict": [["$hex", "ab"]]}));
+    }
+
+    #[test]
+    fn big_integers_are_tagged() {
+        let value = Value::from(u128::MAX);
+        let json = to_json(&value);
+        assert_eq!(json, json!({"$int": u128::MAX.to_string()}));
+        assert_eq!(from_json(&json).unwrap(), value);
+        assert_eq!(from_json(&json!(u64::MAX)).unwrap(), Value::from(u64::MAX));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut pieces = vec![0u8; 40];
+        pieces[7] = 0xff;
+        let value = Value::Dict(BTreeMap::from([
+            (b"pieces".to_vec(), Value::ByteString(pieces)),
+            (
+                b"list".to_vec(),
+                Value::List(vec![Value::Integer(-1), Value::ByteString(b"x".to_vec())]),
+            ),
+            (vec![0xc3], Value::Dict(BTreeMap::new())),
+            (
+                b"tagged".to_vec(),
+                Value::Dict(BTreeMap::from([(b"$base64".to_vec(), Value::Integer(1))])),
+            ),
+        ]));
+        for binary in [BinaryEncoding::Hex, BinaryEncoding::Base64] {
+            let json = to_json_with(&value, binary);
+            assert_eq!(from_json(&json).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn reject_json_without_bencode_equivalent() {
+        for json in [
+            json!(1.5),
+            json!(true),
+            json!(null),
+            json!({"$int": "01"}),
+            json!({"$hex": "abc"}),
+            json!({"$hex": "+a"}),
+            json!({"$hex": 1}),
+            json!({"$base64": "!!"}),
+            json!({"$dict": [[1, 2]]}),
+            json!({"$dict": [["a", 1], ["a", 2]]}),
+        ] {
+            assert!(from_json(&json).is_err(), "{}", json);
+        }
+    }
+}
@@ -1,13 +1,36 @@
+#[cfg(feature = "tokio")]
+pub mod codec;
 pub mod de;
 pub mod decode;
 pub mod encode;
 pub mod error;
+pub mod integer;
+#[cfg(feature = "json")]
+pub mod json;
+mod macros;
+pub mod options;
+pub mod pretty;
+#[cfg(test)]
+mod properties;
+pub mod push;
+pub mod raw;
+pub mod read;
 pub mod ser;
 pub mod value;
 
-pub use de::from_bytes;
-pub use decode::decode;
-pub use encode::encode;
+#[cfg(feature = "tokio")]
+pub use codec::BencodeCodec;
+pub use de::{from_bytes, from_bytes_with_options};
+pub use decode::{decode, decode_ref, decode_with_options};
+pub use encode::{encode, encode_to_writer};
 pub use error::{Error, Result};
-pub use ser::to_bytes;
-pub use value::Value;
+pub use integer::BigInteger;
+#[cfg(feature = "json")]
+pub use json::{BinaryEncoding, from_json, to_json, to_json_with};
+pub use options::DecoderOptions;
+pub use pretty::Pretty;
+pub use push::{Progress, PushDecoder};
+pub use raw::RawBencode;
+pub use read::{ReadDecoder, decode_reader};
+pub use ser::{to_bytes, to_writer};
+pub use value::{Value, ValueIndex, ValueRef, from_value, to_value};
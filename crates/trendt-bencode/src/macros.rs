@@ -0,0 +1,126 @@
+/// Build a [`Value`](crate::Value) from a literal-like description
+///
+/// `key => value` pairs make a dictionary, `[...]` a list and `{...}` a nested
+/// dictionary. Anything else is converted with `Value::from`, so integers,
+/// `&str`, byte string literals and existing values can be used directly.
+///
+/// ```
+/// use trendt_bencode::{bencode, encode};
+///
+/// let torrent = bencode! {
+///     "announce" => "http://tracker.example/announce",
+///     "info" => {
+///         "length" => 42,
+///         "name" => "file.txt",
+///         "pieces" => b"\x00\x01\x02",
+///     },
+///     "url-list" => ["http://a.example/", "http://b.example/"],
+/// };
+/// assert!(encode(&torrent).starts_with(b"d8:announce"));
+/// ```
+#[macro_export]
+macro_rules! bencode {
+    // Dictionary entries, munched one at a time into `$map`
+    (@dict $map:ident) => {};
+    (@dict $map:ident $key:expr => { $($value:tt)* } $(, $($rest:tt)*)?) => {
+        $crate::bencode!(@insert $map $key, $crate::bencode!({ $($value)* }));
+        $crate::bencode!(@dict $map $($($rest)*)?);
+    };
+    (@dict $map:ident $key:expr => [ $($value:tt)* ] $(, $($rest:tt)*)?) => {
+        $crate::bencode!(@insert $map $key, $crate::bencode!([ $($value)* ]));
+        $crate::bencode!(@dict $map $($($rest)*)?);
+    };
+    (@dict $map:ident $key:expr => $value:expr $(, $($rest:tt)*)?) => {
+        $crate::bencode!(@insert $map $key, $crate::Value::from($value));
+        $crate::bencode!(@dict $map $($($rest)*)?);
+    };
+    (@insert $map:ident $key:expr, $value:expr) => {
+        $map.insert(::std::convert::AsRef::<[u8]>::as_ref(&$key).to_vec(), $value);
+    };
+
+    // List items, munched one at a time onto the accumulated `[...]`
+    (@list [$($done:expr,)*]) => {
+        $crate::Value::List(::std::vec![$($done,)*])
+    };
+    (@list [$($done:expr,)*] { $($item:tt)* } $(, $($rest:tt)*)?) => {
+        $crate::bencode!(@list [$($done,)* $crate::bencode!({ $($item)* }),] $($($rest)*)?)
+    };
+    (@list [$($done:expr,)*] [ $($item:tt)* ] $(, $($rest:tt)*)?) => {
+        $crate::bencode!(@list [$($done,)* $crate::bencode!([ $($item)* ]),] $($($rest)*)?)
+    };
+    (@list [$($done:expr,)*] $item:expr $(, $($rest:tt)*)?) => {
+        $crate::bencode!(@list [$($done,)* $crate::Value::from($item),] $($($rest)*)?)
+    };
+
+    () => {
+        $crate::Value::Dict(::std::collections::BTreeMap::new())
+    };
+    ({ $($entries:tt)* }) => {{
+        #[allow(unused_mut)]
+        let mut map = ::std::collections::BTreeMap::new();
+        $crate::bencode!(@dict map $($entries)*);
+        $crate::Value::Dict(map)
+    }};
+    ([ $($items:tt)* ]) => {
+        $crate::bencode!(@list [] $($items)*)
+    };
+    ($value:expr) => {
+        $crate::Value::from($value)
+    };
+    ($($entries:tt)+) => {
+        $crate::bencode!({ $($entries)+ })
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::encode::encode;
+    use crate::value::Value;
+
+    #[test]
+    fn scalars() {
+        assert_eq!(bencode!(42), Value::Integer(42));
+        assert_eq!(bencode!(-1), Value::Integer(-1));
+        assert_eq!(bencode!("spam"), Value::ByteString(b"spam".to_vec()));
+        assert_eq!(bencode!(b"\xff"), Value::ByteString(vec![0xff]));
+        assert_eq!(bencode!([]), Value::List(vec![]));
+        assert_eq!(bencode!({}), Value::Dict(BTreeMap::new()));
+        assert_eq!(bencode!(), Value::Dict(BTreeMap::new()));
+    }
+
+    #[test]
+    fn flat_list_and_dict() {
+        assert_eq!(
+            bencode!([1, 2]),
+            Value::List(vec![Value::Integer(1), Value::Integer(2)])
+        );
+
+        let mut map = BTreeMap::new();
+        map.insert(b"foo".to_vec(), Value::List(vec![Value::Integer(1)]));
+        assert_eq!(bencode! { "foo" => [1] }, Value::Dict(map));
+        assert_eq!(encode(&bencode! { "foo" => 1 }), b"d3:fooi1ee");
+    }
+
+    #[test]
+    fn nested() {
+        let name = String::from("abc");
+        let value = bencode! {
+            "announce" => "url",
+            "info" => {
+                "files" => [
+                    { "length" => 1, "path" => ["a", "b"] },
+                    { "length" => 2 + 3, "path" => ["c"] },
+                ],
+                "name" => name.as_str(),
+                b"pieces" => b"\x00\x01",
+            },
+            "list" => [[], {}, -7, bencode!("x")],
+        };
+        assert_eq!(
+            encode(&value),
+            b"d8:announce3:url4:infod5:filesld6:lengthi1e4:pathl1:a1:beed6:lengthi5e4:pathl1:ceee4:name3:abc6:pieces2:\x00\x01e4:listlledei-7e1:xee"
+        );
+    }
+}
@@ -0,0 +1,70 @@
+/// Validation rules and resource limits applied while decoding
+///
+/// Shared by [`Decoder`](crate::decode::Decoder),
+/// [`ReadDecoder`](crate::read::ReadDecoder) and the serde
+/// [`Deserializer`](crate::de::Deserializer). The limits apply in both modes,
+/// so untrusted input cannot exhaust the stack or memory either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecoderOptions {
+    /// Require canonical encoding: dictionary keys sorted, no leading zeros or `-0`
+    pub canonical: bool,
+    /// Reject input that continues after the top-level value
+    pub reject_trailing_data: bool,
+    /// Maximum nesting depth of lists and dictionaries
+    pub max_depth: usize,
+    /// Maximum length of a single byte string
+    pub max_string_length: usize,
+    /// Maximum number of items in a single list or entries in a single dictionary
+    pub max_items: usize,
+    /// Maximum size of one value buffered by [`PushDecoder`](crate::PushDecoder)
+    /// or the tokio codec while it arrives
+    pub max_buffered: usize,
+    /// Decode integers outside the `i64` range as
+    /// [`Value::BigInteger`](crate::Value::BigInteger) instead of failing
+    pub big_integers: bool,
+}
+
+/// Longest canonical `i64` body: `-9223372036854775808`
+const MAX_INTEGER_LENGTH: usize = 20;
+
+impl DecoderOptions {
+    /// Only accept canonical bencode with nothing after the value
+    pub const fn strict() -> Self {
+        DecoderOptions {
+            canonical: true,
+            reject_trailing_data: true,
+            max_depth: 256,
+            max_string_length: 64 * 1024 * 1024,
+            max_items: 1024 * 1024,
+            max_buffered: 128 * 1024 * 1024,
+            big_integers: false,
+        }
+    }
+
+    /// Accept the non-canonical encodings found in real-world files and responses
+    pub const fn lenient() -> Self {
+        DecoderOptions {
+            canonical: false,
+            reject_trailing_data: false,
+            ..Self::strict()
+        }
+    }
+
+    /// Longest integer or length prefix worth buffering
+    ///
+    /// Leading zeros and big integers can make valid integers arbitrarily
+    /// long, so those are bounded like byte strings instead.
+    pub(crate) fn max_integer_length(&self) -> usize {
+        if self.canonical && !self.big_integers {
+            MAX_INTEGER_LENGTH
+        } else {
+            self.max_string_length
+        }
+    }
+}
+
+impl Default for DecoderOptions {
+    fn default() -> Self {
+        Self::strict()
+    }
+}
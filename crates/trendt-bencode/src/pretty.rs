@@ -0,0 +1,135 @@
+use std::fmt::{self, Write};
+
+use crate::value::Value;
+
+/// Number of bytes of a binary string shown before it is cut off
+const DEFAULT_MAX_BINARY: usize = 16;
+
+/// Human-readable, indented rendering of a [`Value`]
+///
+/// UTF-8 byte strings are quoted; binary strings such as `pieces` are shown
+/// as their length and a hex preview instead of being dumped in full.
+/// Created by [`Value::pretty`].
+#[derive(Debug, Clone, Copy)]
+pub struct Pretty<'a> {
+    value: &'a Value,
+    max_binary: usize,
+}
+
+impl Value {
+    /// Render the value for debugging, see [`Pretty`]
+    pub fn pretty(&self) -> Pretty<'_> {
+        Pretty {
+            value: self,
+            max_binary: DEFAULT_MAX_BINARY,
+        }
+    }
+}
+
+impl Pretty<'_> {
+    /// Show at most `max_binary` bytes of each binary string
+    pub fn max_binary(mut self, max_binary: usize) -> Self {
+        self.max_binary = max_binary;
+        self
+    }
+
+    fn write_value(&self, f: &mut fmt::Formatter<'_>, value: &Value, indent: usize) -> fmt::Result {
+        match value {
+            Value::Integer(n) => write!(f, "{}", n),
+            Value::BigInteger(n) => write!(f, "{}", n),
+            Value::ByteString(bytes) => self.write_bytes(f, bytes),
+            Value::List(items) if items.is_empty() => f.write_str("[]"),
+            Value::List(items) => {
+                f.write_str("[\n")?;
+                for item in items {
+                    write_indent(f, indent + 1)?;
+                    self.write_value(f, item, indent + 1)?;
+                    f.write_str(",\n")?;
+                }
+                write_indent(f, indent)?;
+                f.write_char(']')
+            }
+            Value::Dict(map) if map.is_empty() => f.write_str("{}"),
+            Value::Dict(map) => {
+                f.write_str("{\n")?;
+                for (key, value) in map {
+                    write_indent(f, indent + 1)?;
+                    self.write_bytes(f, key)?;
+                    f.write_str(": ")?;
+                    self.write_value(f, value, indent + 1)?;
+                    f.write_str(",\n")?;
+                }
+                write_indent(f, indent)?;
+                f.write_char('}')
+            }
+        }
+    }
+
+    fn write_bytes(&self, f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+        if let Ok(s) = std::str::from_utf8(bytes) {
+            return write!(f, "{:?}", s);
+        }
+        let shown = bytes.len().min(self.max_binary);
+        write!(f, "<{} bytes: {}", bytes.len(), hex(&bytes[..shown]))?;
+        if shown < bytes.len() {
+            f.write_str("...")?;
+        }
+        f.write_char('>')
+    }
+}
+
+fn write_indent(f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+    for _ in 0..indent {
+        f.write_str("  ")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Pretty<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_value(f, self.value, 0)
+    }
+}
+
+/// Lowercase hex digits of `bytes`
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decode::decode;
+    use crate::value::Value;
+
+    #[test]
+    fn pretty_print_torrent() {
+        let mut input = b"d8:announce3:url4:infod6:lengthi42e4:name3:abc6:pieces20:".to_vec();
+        input.extend((0..20).map(|i| i * 13));
+        input.extend(b"e4:listlee");
+        let value = decode(&input).unwrap();
+
+        let expected = r#"{
+  "announce": "url",
+  "info": {
+    "length": 42,
+    "name": "abc",
+    "pieces": <20 bytes: 000d1a2734414e5b6875828f9ca9b6c3...>,
+  },
+  "list": [],
+}"#;
+        assert_eq!(value.pretty().to_string(), expected);
+    }
+
+    #[test]
+    fn max_binary() {
+        let value = Value::List(vec![Value::ByteString(vec![0xff; 3])]);
+        assert_eq!(
+            value.pretty().max_binary(2).to_string(),
+            "[\n  <3 bytes: ffff...>,\n]"
+        );
+        assert_eq!(
+            value.pretty().max_binary(3).to_string(),
+            "[\n  <3 bytes: ffffff>,\n]"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs;
+use std::path::Path;
+
+use proptest::collection::{btree_map, vec};
+use proptest::prelude::*;
+
+use crate::decode::{Decoder, decode, decode_with_options};
+use crate::encode::encode;
+#[cfg(feature = "json")]
+use crate::json::{from_json, to_json};
+use crate::options::DecoderOptions;
+use crate::push::{Progress, PushDecoder};
+use crate::read::ReadDecoder;
+use crate::ser::to_bytes;
+use crate::value::{Value, from_value, to_value};
+
+/// Strict decoding, but keeping integers too large for `i64`
+const BIG_INTEGERS: DecoderOptions = DecoderOptions {
+    big_integers: true,
+    ..DecoderOptions::strict()
+};
+
+/// Arbitrary value trees, biased towards short keys so dicts collide and nest
+fn arb_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        any::<i64>().prop_map(Value::Integer),
+        any::<i128>().prop_map(Value::from),
+        vec(any::<u8>(), 0..32).prop_map(Value::ByteString),
+        "[a-z]{0,8}".prop_map(Value::from),
+    ];
+    leaf.prop_recursive(6, 128, 8, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..8).prop_map(Value::List),
+            btree_map(vec(any::<u8>(), 0..6), inner, 0..8).prop_map(Value::Dict),
+        ]
+    })
+}
+
+proptest! {
+    #[test]
+    fn decode_inverts_encode(value in arb_value()) {
+        let encoded = encode(&value);
+        prop_assert_eq!(decode_with_options(&encoded, BIG_INTEGERS)?, value.clone());
+        let borrowed = Decoder::with_options(&encoded, BIG_INTEGERS).decode_value_ref()?;
+        prop_assert_eq!(Value::from(borrowed), value.clone());
+        let mut reader = ReadDecoder::with_options(&encoded[..], BIG_INTEGERS);
+        prop_assert_eq!(reader.decode_value()?, value);
+    }
+
+    #[test]
+    fn serde_matches_encode(value in arb_value()) {
+        prop_assert_eq!(to_bytes(&value)?, encode(&value));
+        prop_assert_eq!(to_value(&value)?, value.clone());
+        prop_assert_eq!(from_value::<Value>(value.clone())?, value);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip(value in arb_value()) {
+        prop_assert_eq!(from_json(&to_json(&value))?, value);
+    }
+
+    #[test]
+    fn push_decoder_any_split(value in arb_value(), split in any::<prop::sample::Index>()) {
+        let encoded = encode(&value);
+        let split = split.index(encoded.len() + 1);
+        let mut decoder = PushDecoder::with_options(BIG_INTEGERS);
+        // The value only completes on its last byte, so the first feed finishes it
+        // exactly when it holds everything
+        let (progress, consumed) = match decoder.feed(&encoded[..split])? {
+            Progress::NeedMore => (decoder.feed(&encoded[split..])?, encoded.len() - split),
+            done => (done, encoded.len()),
+        };
+        prop_assert_eq!(progress, Progress::Done { value, consumed });
+    }
+
+    #[test]
+    fn arbitrary_bytes_never_panic(input in vec(any::<u8>(), 0..64)) {
+        let _ = decode(&input);
+        let _ = decode_with_options(&input, DecoderOptions::lenient());
+        let _ = PushDecoder::new().feed(&input);
+    }
+}
+
+/// Replay the committed fuzz corpus, so crashes found once stay fixed
+#[test]
+fn fuzz_corpus() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus");
+    for target in fs::read_dir(&corpus).unwrap() {
+        for entry in fs::read_dir(target.unwrap().path()).unwrap() {
+            let input = fs::read(entry.unwrap().path()).unwrap();
+            if let Ok(value) = decode(&input) {
+                assert_eq!(encode(&value), input);
+            }
+            if let Ok(value) = decode_with_options(&input, DecoderOptions::lenient()) {
+                assert_eq!(decode(&encode(&value)).unwrap(), value);
+            }
+        }
+    }
+}
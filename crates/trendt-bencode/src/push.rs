@@ -0,0 +1,448 @@
+use crate::decode::Decoder;
+use crate::error::{Error, KeyPath, Result};
+use crate::options::DecoderOptions;
+use crate::value::Value;
+
+/// Outcome of feeding a chunk to a [`PushDecoder`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Progress {
+    /// The value is not complete yet; everything fed so far has been kept
+    NeedMore,
+    /// A complete value was decoded
+    Done {
+        /// The decoded value
+        value: Value,
+        /// How many bytes of the last chunk belong to the value
+        consumed: usize,
+    },
+}
+
+/// What the scanner expects next
+#[derive(Debug, Clone, Copy)]
+enum State {
+    /// The start of a value, or `e` closing the current list or dictionary
+    Value,
+    /// The body of an integer, with its length so far
+    Integer(usize),
+    /// The length prefix of a byte string
+    Length(usize),
+    /// The data of a byte string, with the number of bytes left
+    Data(usize),
+}
+
+/// A resumable decoder for input that arrives in arbitrary chunks
+///
+/// Chunks are buffered and scanned once each to find where the value ends, so
+/// feeding many small chunks stays linear. The complete value is then decoded
+/// with the same rules as [`Decoder`]. After [`Progress::Done`] the decoder is
+/// empty again; bytes of the last chunk past `consumed` belong to the next
+/// value and should be fed again.
+///
+/// The depth, length, item and buffer limits in [`DecoderOptions`] are
+/// checked as chunks arrive, so a peer cannot make the decoder buffer an
+/// unbounded value.
+///
+/// After an error the decoder is reset as well, but since the stream has no
+/// way to resynchronise the connection should usually be dropped.
+#[derive(Debug)]
+pub struct PushDecoder {
+    /// Bytes of the current value received so far
+    buffer: Vec<u8>,
+    /// Finds where the value in `buffer` ends
+    scanner: FrameScanner,
+}
+
+impl Default for PushDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PushDecoder {
+    /// Create a new decoder
+    pub fn new() -> Self {
+        Self::with_options(DecoderOptions::default())
+    }
+
+    /// Create a new decoder with custom validation rules and limits
+    pub fn with_options(options: DecoderOptions) -> Self {
+        PushDecoder {
+            buffer: Vec::new(),
+            scanner: FrameScanner::new(options),
+        }
+    }
+
+    /// Number of bytes buffered for the value in progress
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Feed the next chunk of input
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Progress> {
+        let start = self.buffer.len();
+        self.buffer.extend_from_slice(chunk);
+
+        let result = match self.scanner.scan(&self.buffer) {
+            Ok(Some(end)) => Decoder::with_options(&self.buffer[..end], self.scanner.options)
+                .decode_value()
+                .map(|value| Progress::Done {
+                    value,
+                    consumed: end - start,
+                }),
+            Ok(None) => return Ok(Progress::NeedMore),
+            Err(err) => Err(err),
+        };
+        self.reset();
+        result
+    }
+
+    /// Forget any partial value
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.scanner.reset();
+    }
+}
+
+/// A list or dictionary open at the scan position
+#[derive(Debug, Clone, Copy)]
+struct Container {
+    /// Whether this is a dictionary, whose children alternate between keys and values
+    dict: bool,
+    /// Number of child values started so far
+    children: usize,
+}
+
+impl Container {
+    /// Number of list items or dictionary entries started so far
+    fn items(&self) -> usize {
+        if self.dict {
+            self.children.div_ceil(2)
+        } else {
+            self.children
+        }
+    }
+}
+
+/// Finds the end of the first value in a growing buffer
+///
+/// Remembers how far it got, so each byte is only looked at once however
+/// the buffer fills up. Only structure and limits are checked here; the
+/// complete value still has to be decoded.
+#[derive(Debug)]
+pub(crate) struct FrameScanner {
+    /// Number of bytes of the buffer already scanned
+    scanned: usize,
+    /// What the scanner expects next
+    state: State,
+    /// Lists and dictionaries open at the scan position, innermost last
+    open: Vec<Container>,
+    /// Validation rules and limits
+    pub(crate) options: DecoderOptions,
+}
+
+impl FrameScanner {
+    pub(crate) fn new(options: DecoderOptions) -> Self {
+        FrameScanner {
+            scanned: 0,
+            state: State::Value,
+            open: Vec::new(),
+            options,
+        }
+    }
+
+    /// Start over at the beginning of a new buffer
+    pub(crate) fn reset(&mut self) {
+        self.scanned = 0;
+        self.state = State::Value;
+        self.open.clear();
+    }
+
+    /// Scan the bytes added to `buffer` since the last call
+    ///
+    /// Returns the length of the value once it is complete. Errors carry
+    /// their offset into `buffer`.
+    pub(crate) fn scan(&mut self, buffer: &[u8]) -> Result<Option<usize>> {
+        let result = match self.scan_bytes(buffer) {
+            // Everything buffered so far belongs to the incomplete value
+            Ok(None) if buffer.len() > self.options.max_buffered => {
+                Err(Error::BufferLimitExceeded(buffer.len()))
+            }
+            Ok(Some(end)) if end > self.options.max_buffered => {
+                Err(Error::BufferLimitExceeded(end))
+            }
+            result => result,
+        };
+        result.map_err(|err| KeyPath::default().locate(err, self.scanned))
+    }
+
+    /// Count the start of a value towards the limits of its container
+    fn start_value(&mut self) -> Result<()> {
+        if let Some(container) = self.open.last_mut() {
+            container.children += 1;
+            if container.items() > self.options.max_items {
+                return Err(Error::ItemLimitExceeded);
+            }
+        }
+        Ok(())
+    }
+
+    fn scan_bytes(&mut self, buffer: &[u8]) -> Result<Option<usize>> {
+        while let Some(&byte) = buffer.get(self.scanned) {
+            let complete = match self.state {
+                State::Value => match byte {
+                    b'i' => {
+                        self.start_value()?;
+                        self.state = State::Integer(0);
+                        false
+                    }
+                    b'l' | b'd' => {
+                        self.start_value()?;
+                        if self.open.len() >= self.options.max_depth {
+                            return Err(Error::DepthLimitExceeded);
+                        }
+                        self.open.push(Container {
+                            dict: byte == b'd',
+                            children: 0,
+                        });
+                        false
+                    }
+                    b'e' if !self.open.is_empty() => {
+                        self.open.pop();
+                        true
+                    }
+                    b'0'..=b'9' => {
+                        self.start_value()?;
+                        self.state = State::Length(usize::from(byte - b'0'));
+                        false
+                    }
+                    _ => return Err(Error::InvalidCharacter(byte)),
+                },
+                State::Integer(_) if byte == b'e' => {
+                    self.state = State::Value;
+                    true
+                }
+                State::Integer(length) if length >= self.options.max_integer_length() => {
+                    return Err(Error::InvalidInteger);
+                }
+                State::Integer(length) => {
+                    self.state = State::Integer(length + 1);
+                    false
+                }
+                State::Length(0) if byte == b':' => {
+                    self.state = State::Value;
+                    true
+                }
+                State::Length(length) if byte == b':' => {
+                    // Fail before buffering a string that cannot fit
+                    let end = self.scanned.saturating_add(length).saturating_add(1);
+                    if end > self.options.max_buffered {
+                        return Err(Error::BufferLimitExceeded(end));
+                    }
+                    self.state = State::Data(length);
+                    false
+                }
+                State::Length(length) if byte.is_ascii_digit() => {
+                    let length = length
+                        .checked_mul(10)
+                        .and_then(|n| n.checked_add(usize::from(byte - b'0')))
+                        .unwrap_or(usize::MAX);
+                    if length > self.options.max_string_length {
+                        return Err(Error::LengthLimitExceeded(length));
+                    }
+                    self.state = State::Length(length);
+                    false
+                }
+                State::Length(_) => return Err(Error::InvalidCharacter(byte)),
+                State::Data(remaining) => {
+                    // Skip as much of the string as has arrived in one go
+                    let available = (buffer.len() - self.scanned).min(remaining);
+                    self.scanned += available - 1;
+                    if available == remaining {
+                        self.state = State::Value;
+                        true
+                    } else {
+                        self.state = State::Data(remaining - available);
+                        false
+                    }
+                }
+            };
+            self.scanned += 1;
+
+            if complete && self.open.is_empty() {
+                return Ok(Some(self.scanned));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::decode;
+
+    const INPUT: &[u8] = b"d8:announce3:url4:infod6:lengthi42e4:name3:abcee";
+
+    #[test]
+    fn byte_at_a_time() {
+        let mut decoder = PushDecoder::new();
+        let (last, rest) = INPUT.split_last().unwrap();
+        for byte in rest {
+            assert_eq!(decoder.feed(&[*byte]).unwrap(), Progress::NeedMore);
+        }
+        assert_eq!(decoder.buffered(), rest.len());
+        assert_eq!(
+            decoder.feed(&[*last]).unwrap(),
+            Progress::Done {
+                value: decode(INPUT).unwrap(),
+                consumed: 1
+            }
+        );
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn every_split_point() {
+        let expected = decode(INPUT).unwrap();
+        for split in 1..INPUT.len() {
+            let mut decoder = PushDecoder::new();
+            assert_eq!(decoder.feed(&INPUT[..split]).unwrap(), Progress::NeedMore);
+            assert_eq!(
+                decoder.feed(&INPUT[split..]).unwrap(),
+                Progress::Done {
+                    value: expected.clone(),
+                    consumed: INPUT.len() - split
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn back_to_back_values() {
+        let mut decoder = PushDecoder::new();
+        let mut chunk = &b"i1e0:li2e"[..];
+        let mut values = Vec::new();
+        while let Progress::Done { value, consumed } = decoder.feed(chunk).unwrap() {
+            values.push(value);
+            chunk = &chunk[consumed..];
+        }
+        assert_eq!(values, [Value::Integer(1), Value::ByteString(vec![])]);
+        assert_eq!(
+            decoder.feed(b"e").unwrap(),
+            Progress::Done {
+                value: Value::List(vec![Value::Integer(2)]),
+                consumed: 1
+            }
+        );
+    }
+
+    #[test]
+    fn scalars_complete_immediately() {
+        let mut decoder = PushDecoder::new();
+        assert_eq!(
+            decoder.feed(b"4:spam").unwrap(),
+            Progress::Done {
+                value: Value::ByteString(b"spam".to_vec()),
+                consumed: 6
+            }
+        );
+        assert_eq!(decoder.feed(b"i-1").unwrap(), Progress::NeedMore);
+        assert_eq!(
+            decoder.feed(b"e").unwrap(),
+            Progress::Done {
+                value: Value::Integer(-1),
+                consumed: 1
+            }
+        );
+    }
+
+    #[test]
+    fn errors_reset_the_decoder() {
+        let mut decoder = PushDecoder::new();
+        assert_eq!(decoder.feed(b"d3:fooi1e").unwrap(), Progress::NeedMore);
+        let err = decoder.feed(b"3:bari2ee").unwrap_err();
+        assert!(matches!(err.inner(), Error::UnsortedDictKeys));
+        assert_eq!(decoder.buffered(), 0);
+
+        let err = decoder.feed(b"lx").unwrap_err();
+        assert!(matches!(err.inner(), Error::InvalidCharacter(b'x')));
+        assert_eq!(err.position(), Some(1));
+    }
+
+    #[test]
+    fn limits_apply_before_the_value_completes() {
+        let options = DecoderOptions {
+            max_depth: 2,
+            max_string_length: 100,
+            ..DecoderOptions::strict()
+        };
+        let mut decoder = PushDecoder::with_options(options);
+        let err = decoder.feed(b"lll").unwrap_err();
+        assert!(matches!(err.inner(), Error::DepthLimitExceeded));
+
+        let err = decoder.feed(b"1000").unwrap_err();
+        assert!(matches!(err.inner(), Error::LengthLimitExceeded(1000)));
+
+        let err = decoder.feed(&[b'9'; 30]).unwrap_err();
+        assert!(matches!(err.inner(), Error::LengthLimitExceeded(_)));
+
+        let mut integer = b"i".to_vec();
+        integer.extend([b'1'; 25]);
+        let err = decoder.feed(&integer).unwrap_err();
+        assert!(matches!(err.inner(), Error::InvalidInteger));
+    }
+
+    #[test]
+    fn endless_containers_hit_the_item_limit() {
+        let options = DecoderOptions {
+            max_items: 100,
+            ..DecoderOptions::lenient()
+        };
+        let mut decoder = PushDecoder::with_options(options);
+        assert_eq!(decoder.feed(b"l").unwrap(), Progress::NeedMore);
+        for _ in 0..100 {
+            assert_eq!(decoder.feed(b"i0e").unwrap(), Progress::NeedMore);
+        }
+        let err = decoder.feed(b"i0e").unwrap_err();
+        assert!(matches!(err.inner(), Error::ItemLimitExceeded));
+        assert_eq!(decoder.buffered(), 0);
+
+        // Keys and values together make up one entry
+        let mut input = b"d".to_vec();
+        input.extend(b"0:0:".repeat(100));
+        assert_eq!(decoder.feed(&input).unwrap(), Progress::NeedMore);
+        let err = decoder.feed(b"0:").unwrap_err();
+        assert!(matches!(err.inner(), Error::ItemLimitExceeded));
+
+        // Nested containers count for their parent only
+        let mut input = b"l".to_vec();
+        input.extend(b"li0ee".repeat(100));
+        input.push(b'e');
+        assert!(matches!(
+            decoder.feed(&input).unwrap(),
+            Progress::Done { .. }
+        ));
+    }
+
+    #[test]
+    fn enforce_buffer_limit() {
+        let options = DecoderOptions {
+            max_buffered: 1000,
+            ..DecoderOptions::lenient()
+        };
+        let mut decoder = PushDecoder::with_options(options);
+        assert_eq!(decoder.feed(b"l").unwrap(), Progress::NeedMore);
+        let err = loop {
+            match decoder.feed(b"i0e") {
+                Ok(progress) => assert_eq!(progress, Progress::NeedMore),
+                Err(err) => break err,
+            }
+        };
+        assert!(matches!(err.inner(), Error::BufferLimitExceeded(1003)));
+        assert_eq!(decoder.buffered(), 0);
+
+        // A string that cannot fit is rejected from its length alone
+        let err = decoder.feed(b"l999:").unwrap_err();
+        assert!(matches!(err.inner(), Error::BufferLimitExceeded(1004)));
+    }
+}
@@ -0,0 +1,168 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::decode::Decoder;
+use crate::error::Result;
+use crate::options::DecoderOptions;
+
+/// Newtype name used to signal raw passthrough to the bencode (de)serializer
+pub(crate) const TOKEN: &str = "$trendt_bencode::private::RawBencode";
+
+/// The exact encoded bytes of a single bencode value
+///
+/// Used as a serde field (e.g. `info: RawBencode<'a>`), it captures the
+/// original bytes of that value instead of parsing it. When serialized
+/// through this crate the bytes are written back verbatim.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RawBencode<'a> {
+    bytes: Cow<'a, [u8]>,
+}
+
+impl<'a> RawBencode<'a> {
+    /// Wrap bytes that must hold exactly one well-formed bencode value
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self> {
+        validate(bytes)?;
+        Ok(RawBencode {
+            bytes: Cow::Borrowed(bytes),
+        })
+    }
+
+    /// The encoded bytes of the value
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Detach from the input buffer
+    pub fn into_owned(self) -> RawBencode<'static> {
+        RawBencode {
+            bytes: Cow::Owned(self.bytes.into_owned()),
+        }
+    }
+}
+
+/// Check that `bytes` hold exactly one complete value
+///
+/// Non-canonical values are accepted, since lenient decoding captures them
+/// as they appeared in the input.
+fn validate(bytes: &[u8]) -> Result<()> {
+    let mut decoder = Decoder::with_options(bytes, DecoderOptions::lenient());
+    decoder.skip_value()?;
+    decoder.end()
+}
+
+impl Serialize for RawBencode<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(TOKEN, &RawBytes(&self.bytes))
+    }
+}
+
+/// Serializes as a byte string, which our serializer writes unframed under `TOKEN`
+struct RawBytes<'a>(&'a [u8]);
+
+impl Serialize for RawBytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for RawBencode<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(TOKEN, RawVisitor(PhantomData))
+    }
+}
+
+/// Accepts byte strings holding one encoded value
+///
+/// Any deserializer can hand us bytes here, not just ours, so they are
+/// validated before the serializer is trusted to write them out verbatim.
+struct RawVisitor<'a>(PhantomData<&'a ()>);
+
+impl<'de: 'a, 'a> Visitor<'de> for RawVisitor<'a> {
+    type Value = RawBencode<'a>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a raw bencode value")
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(
+        self,
+        v: &'de [u8],
+    ) -> std::result::Result<Self::Value, E> {
+        validate(v).map_err(E::custom)?;
+        Ok(RawBencode {
+            bytes: Cow::Borrowed(v),
+        })
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> std::result::Result<Self::Value, E> {
+        validate(v).map_err(E::custom)?;
+        Ok(RawBencode {
+            bytes: Cow::Owned(v.to_vec()),
+        })
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> std::result::Result<Self::Value, E> {
+        validate(&v).map_err(E::custom)?;
+        Ok(RawBencode {
+            bytes: Cow::Owned(v),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{from_bytes, to_bytes};
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Metainfo<'a> {
+        announce: String,
+        #[serde(borrow)]
+        info: RawBencode<'a>,
+    }
+
+    #[test]
+    fn capture_raw_dict() {
+        let input = b"d8:announce3:url4:infod6:lengthi42e4:name3:abcee";
+        let m: Metainfo = from_bytes(input).unwrap();
+        assert_eq!(m.announce, "url");
+        assert_eq!(m.info.as_bytes(), b"d6:lengthi42e4:name3:abce");
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        let input = b"d8:announce3:url4:infod6:lengthi42e4:name3:abcee";
+        let m: Metainfo = from_bytes(input).unwrap();
+        assert_eq!(to_bytes(&m).unwrap(), input);
+    }
+
+    #[test]
+    fn from_bytes_validates() {
+        assert!(RawBencode::from_bytes(b"li1ee").is_ok());
+        assert!(RawBencode::from_bytes(b"li1e").is_err());
+        assert!(RawBencode::from_bytes(b"i1ei2e").is_err());
+    }
+
+    #[test]
+    fn foreign_bytes_are_validated() {
+        use serde::de::value::{BorrowedBytesDeserializer, BytesDeserializer};
+
+        use crate::error::Error;
+
+        let raw = RawBencode::deserialize(BytesDeserializer::<Error>::new(b"li1ee")).unwrap();
+        assert_eq!(raw.as_bytes(), b"li1ee");
+
+        for input in [&b"li1e"[..], b"i1ei2e", b"junk", b""] {
+            assert!(RawBencode::deserialize(BytesDeserializer::<Error>::new(input)).is_err());
+            assert!(
+                RawBencode::deserialize(BorrowedBytesDeserializer::<Error>::new(input)).is_err()
+            );
+        }
+    }
+}
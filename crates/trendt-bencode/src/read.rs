@@ -0,0 +1,349 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+
+use crate::decode::{DictKeys, parse_length};
+use crate::error::{Error, KeyPath, Result};
+use crate::integer::{self, Integer, integer_text};
+use crate::options::DecoderOptions;
+use crate::value::Value;
+
+/// A decoder that pulls bencode data from any `std::io::Read`
+///
+/// Input is buffered internally, so the reader does not need to be buffered
+/// already. Several values can be decoded back to back from the same stream.
+pub struct ReadDecoder<R> {
+    /// The buffered input
+    reader: BufReader<R>,
+    /// Number of bytes consumed so far
+    position: usize,
+    /// Validation rules and limits
+    options: DecoderOptions,
+    /// Current nesting depth of lists and dictionaries
+    depth: usize,
+    /// Path to the value being decoded, for error reporting
+    path: KeyPath<'static>,
+}
+
+impl<R: Read> ReadDecoder<R> {
+    /// Create a new decoder reading from `reader`
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, DecoderOptions::default())
+    }
+
+    /// Create a new decoder with custom validation rules and limits
+    pub fn with_options(reader: R, options: DecoderOptions) -> Self {
+        ReadDecoder {
+            reader: BufReader::new(reader),
+            position: 0,
+            options,
+            depth: 0,
+            path: KeyPath::default(),
+        }
+    }
+
+    /// Check that the reader has no data left
+    pub fn end(&mut self) -> Result<()> {
+        match self.peek() {
+            Ok(_) => Err(self.path.locate(Error::TrailingData, self.position)),
+            Err(Error::UnexpectedEof) => Ok(()),
+            Err(err) => Err(self.path.locate(err, self.position)),
+        }
+    }
+
+    /// Number of bytes consumed from the reader so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Peek at the current byte without advancing
+    fn peek(&mut self) -> Result<u8> {
+        let buffer = self.reader.fill_buf()?;
+        buffer.first().copied().ok_or(Error::UnexpectedEof)
+    }
+
+    /// Consume and return the current byte
+    fn next(&mut self) -> Result<u8> {
+        let byte = self.peek()?;
+        self.reader.consume(1);
+        self.position += 1;
+        Ok(byte)
+    }
+
+    /// Expect a specific byte, error if mismatch
+    fn expect(&mut self, expected: u8) -> Result<()> {
+        // Check before consuming so errors point at the offending byte
+        let byte = self.peek()?;
+        if byte != expected {
+            return Err(Error::InvalidCharacter(byte));
+        }
+        self.next()?;
+        Ok(())
+    }
+
+    /// Decode an integer: i<number>e
+    fn decode_integer(&mut self) -> Result<Value> {
+        self.expect(b'i')?;
+
+        let mut digits = Vec::new();
+        while self.peek()? != b'e' {
+            if digits.len() >= self.options.max_integer_length() {
+                return Err(Error::InvalidInteger);
+            }
+            digits.push(self.next()?);
+        }
+        let text = integer_text(&digits, &self.options)?;
+        let number = match integer::parse(text, self.options.big_integers)? {
+            Integer::Small(n) => Value::Integer(n),
+            Integer::Big(n) => Value::BigInteger(n),
+        };
+
+        self.expect(b'e')?;
+
+        Ok(number)
+    }
+
+    /// Decode a byte string: <length>:<data>
+    fn decode_byte_string(&mut self) -> Result<Vec<u8>> {
+        let mut digits = Vec::new();
+        loop {
+            let byte = self.peek()?;
+            if byte == b':' {
+                break;
+            }
+            if !byte.is_ascii_digit() {
+                return Err(Error::InvalidCharacter(byte));
+            }
+            if digits.len() >= self.options.max_integer_length() {
+                return Err(Error::LengthLimitExceeded(digits.len()));
+            }
+            digits.push(self.next()?);
+        }
+        let length = parse_length(&digits, &self.options)?;
+
+        self.expect(b':')?;
+
+        // Read through `take` so a bogus length cannot force a huge allocation up front
+        let mut data = Vec::new();
+        (&mut self.reader)
+            .take(length as u64)
+            .read_to_end(&mut data)?;
+        self.position += data.len();
+        if data.len() < length {
+            return Err(Error::UnexpectedEof);
+        }
+
+        Ok(data)
+    }
+
+    /// Decode any bencode value
+    pub fn decode_value(&mut self) -> Result<Value> {
+        self.decode_any()
+            .map_err(|err| self.path.locate(err, self.position))
+    }
+
+    fn decode_any(&mut self) -> Result<Value> {
+        match self.peek()? {
+            b'i' => self.decode_integer(),
+            b'l' => self.decode_list(),
+            b'd' => self.decode_dict(),
+            b'0'..=b'9' => Ok(Value::ByteString(self.decode_byte_string()?)),
+            byte => Err(Error::InvalidCharacter(byte)),
+        }
+    }
+
+    /// Track entering a list or dictionary, enforcing the depth limit
+    fn enter(&mut self) -> Result<()> {
+        if self.depth >= self.options.max_depth {
+            return Err(Error::DepthLimitExceeded);
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Decode a list: l<items>e
+    fn decode_list(&mut self) -> Result<Value> {
+        self.expect(b'l')?;
+        self.enter()?;
+
+        let mut items = Vec::new();
+        while self.peek()? != b'e' {
+            if items.len() >= self.options.max_items {
+                return Err(Error::ItemLimitExceeded);
+            }
+            self.path.push_index(items.len());
+            let item = self.decode_value();
+            self.path.pop();
+            items.push(item?);
+        }
+
+        self.expect(b'e')?;
+        self.depth -= 1;
+
+        Ok(Value::List(items))
+    }
+
+    /// Decode a dictionary: d<pairs>e
+    fn decode_dict(&mut self) -> Result<Value> {
+        self.expect(b'd')?;
+        self.enter()?;
+
+        let mut map = BTreeMap::new();
+        let mut keys = DictKeys::new(&self.options);
+
+        while self.peek()? != b'e' {
+            // Keys must be byte strings
+            if !self.peek()?.is_ascii_digit() {
+                return Err(Error::InvalidDictKey);
+            }
+            if map.len() >= self.options.max_items {
+                return Err(Error::ItemLimitExceeded);
+            }
+
+            let key = self.decode_byte_string()?;
+            keys.check(key.clone())?;
+
+            // Lend the key to the path rather than copying it
+            self.path.push_key(key);
+            let value = self.decode_value();
+            let key = self.path.pop_key().unwrap_or_default().into_owned();
+
+            map.insert(key, value?);
+        }
+
+        self.expect(b'e')?;
+        self.depth -= 1;
+
+        Ok(Value::Dict(map))
+    }
+}
+
+/// Convenience function to decode a single Value from a reader
+///
+/// Under the default (strict) options the reader must end after the value.
+pub fn decode_reader<R: Read>(reader: R) -> Result<Value> {
+    let options = DecoderOptions::default();
+    let mut decoder = ReadDecoder::with_options(reader, options);
+    let value = decoder.decode_value()?;
+    if options.reject_trailing_data {
+        decoder.end()?;
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+    use crate::decode::decode;
+
+    /// A reader that hands out at most one byte per call
+    struct OneByte<'a>(&'a [u8]);
+
+    impl Read for OneByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((byte, rest)), Some(slot)) => {
+                    *slot = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn matches_slice_decoder() {
+        let input = b"d3:bar4:spam3:fooli1ei-2ee4:infod6:lengthi42eee";
+        let expected = decode(input).unwrap();
+        assert_eq!(decode_reader(&input[..]).unwrap(), expected);
+        assert_eq!(decode_reader(OneByte(input)).unwrap(), expected);
+    }
+
+    #[test]
+    fn decode_consecutive_values() {
+        let mut decoder = ReadDecoder::new(&b"i1e4:spamle"[..]);
+        assert_eq!(decoder.decode_value().unwrap(), Value::Integer(1));
+        assert_eq!(
+            decoder.decode_value().unwrap(),
+            Value::ByteString(b"spam".to_vec())
+        );
+        assert_eq!(decoder.decode_value().unwrap(), Value::List(vec![]));
+        assert_eq!(decoder.position(), 11);
+    }
+
+    #[test]
+    fn reject_truncated_byte_string() {
+        let err = decode_reader(OneByte(b"10:short")).unwrap_err();
+        assert!(matches!(err.inner(), Error::UnexpectedEof));
+    }
+
+    #[test]
+    fn reject_unsorted_dict_keys() {
+        let err = decode_reader(&b"d3:fooi1e3:bari2ee"[..]).unwrap_err();
+        assert!(matches!(err.inner(), Error::UnsortedDictKeys));
+    }
+
+    #[test]
+    fn reader_errors_surface_as_io() {
+        struct Failing;
+
+        impl Read for Failing {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("connection reset"))
+            }
+        }
+
+        let err = decode_reader(Failing).unwrap_err();
+        assert!(matches!(err.inner(), Error::Io(_)));
+    }
+
+    #[test]
+    fn error_matches_slice_decoder() {
+        let input = b"d4:infod5:filesld6:lengthi1eed6:lengthi01eeeee";
+        let expected = decode(input).unwrap_err();
+        let err = decode_reader(OneByte(input)).unwrap_err();
+        assert_eq!(err.path(), expected.path());
+        assert_eq!(err.position(), expected.position());
+    }
+
+    #[test]
+    fn options_apply_to_reader() {
+        let err = decode_reader(&b"i1ei2e"[..]).unwrap_err();
+        assert!(matches!(err.inner(), Error::TrailingData));
+
+        let mut decoder =
+            ReadDecoder::with_options(&b"d3:fooi1e3:bari2ee"[..], DecoderOptions::lenient());
+        assert!(decoder.decode_value().is_ok());
+
+        let options = DecoderOptions {
+            max_string_length: 4,
+            ..DecoderOptions::strict()
+        };
+        let mut decoder = ReadDecoder::with_options(&b"5:spams"[..], options);
+        let err = decoder.decode_value().unwrap_err();
+        assert!(matches!(err.inner(), Error::LengthLimitExceeded(5)));
+    }
+
+    #[test]
+    fn endless_digits_are_bounded() {
+        let endless = |prefix: &'static [u8], digit: u8| prefix.chain(io::repeat(digit));
+        let err = decode_reader(endless(b"i", b'1')).unwrap_err();
+        assert!(matches!(err.inner(), Error::InvalidInteger));
+        let err = decode_reader(endless(b"", b'1')).unwrap_err();
+        assert!(matches!(err.inner(), Error::LengthLimitExceeded(20)));
+
+        // Leading zeros are allowed here, so only the string limit stops them
+        let options = DecoderOptions {
+            max_string_length: 1000,
+            ..DecoderOptions::lenient()
+        };
+        let mut decoder = ReadDecoder::with_options(endless(b"i", b'0'), options);
+        let err = decoder.decode_value().unwrap_err();
+        assert!(matches!(err.inner(), Error::InvalidInteger));
+        let mut decoder = ReadDecoder::with_options(endless(b"", b'0'), options);
+        let err = decoder.decode_value().unwrap_err();
+        assert!(matches!(err.inner(), Error::LengthLimitExceeded(1000)));
+    }
+}
@@ -1,26 +1,90 @@
+use std::io::{self, Write};
+use std::ops::Range;
+
 use serde::ser::{self, Serialize};
 
 use crate::error::{Error, Result};
+use crate::raw;
 
 /// A serializer for bencode data
-pub struct Serializer {
-    output: Vec<u8>,
+pub struct Serializer<W> {
+    output: Counter<W>,
+    /// Set while serializing a `RawBencode`, whose bytes are written verbatim
+    raw: bool,
 }
 
-impl Serializer {
-    pub fn new() -> Self {
-        Serializer { output: Vec::new() }
+impl<W: Write> Serializer<W> {
+    /// Create a serializer writing to `output`
+    pub fn new(output: W) -> Self {
+        Serializer {
+            output: Counter {
+                inner: output,
+                written: 0,
+            },
+            raw: false,
+        }
+    }
+
+    /// Unwrap the underlying writer
+    pub fn into_inner(self) -> W {
+        self.output.inner
+    }
+
+    /// Serialize a list item or variant payload, which must encode to a value
+    ///
+    /// `None` and unit encode to nothing. Dictionaries leave such entries out,
+    /// but here that would shift the remaining items or leave a key without
+    /// a value, so it is an error instead.
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let start = self.output.written;
+        value.serialize(&mut *self)?;
+        if self.output.written == start {
+            return Err(missing_value());
+        }
+        Ok(())
+    }
+}
+
+/// The error for `None` or unit where bencode needs a value
+pub(crate) fn missing_value() -> Error {
+    Error::Message("bencode cannot represent a missing value".into())
+}
+
+/// A writer that counts the bytes passing through it
+struct Counter<W> {
+    inner: W,
+    written: usize,
+}
+
+impl<W: Write> Write for Counter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
     }
 }
 
 /// Serialize a value to bencode bytes
-pub fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>> {
-    let mut serializer = Serializer::new();
-    value.serialize(&mut serializer)?;
-    Ok(serializer.output)
+pub fn to_bytes<T: ?Sized + Serialize>(value: &T) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    to_writer(&mut output, value)?;
+    Ok(output)
+}
+
+/// Serialize a value as bencode directly into a writer
+///
+/// Every token is a separate write, so wrap unbuffered writers such as files
+/// and sockets in a [`BufWriter`](std::io::BufWriter).
+pub fn to_writer<W: Write, T: ?Sized + Serialize>(writer: W, value: &T) -> Result<()> {
+    let mut serializer = Serializer::new(writer);
+    value.serialize(&mut serializer)
 }
 
-impl<'a> ser::Serializer for &'a mut Serializer {
+impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -28,9 +92,9 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     type SerializeTuple = Self;
     type SerializeTupleStruct = Self;
     type SerializeTupleVariant = Self;
-    type SerializeMap = SortedMapSerializer<'a>;
-    type SerializeStruct = SortedMapSerializer<'a>;
-    type SerializeStructVariant = Self;
+    type SerializeMap = SortedMapSerializer<'a, W>;
+    type SerializeStruct = SortedMapSerializer<'a, W>;
+    type SerializeStructVariant = SortedMapSerializer<'a, W>;
 
     fn serialize_bool(self, v: bool) -> Result<()> {
         self.serialize_i64(if v { 1 } else { 0 })
@@ -49,9 +113,7 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_i64(self, v: i64) -> Result<()> {
-        self.output.push(b'i');
-        self.output.extend(v.to_string().as_bytes());
-        self.output.push(b'e');
+        write!(self.output, "i{}e", v)?;
         Ok(())
     }
 
@@ -67,8 +129,19 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         self.serialize_i64(v as i64)
     }
 
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        write!(self.output, "i{}e", v)?;
+        Ok(())
+    }
+
     fn serialize_u64(self, v: u64) -> Result<()> {
-        self.serialize_i64(v as i64)
+        write!(self.output, "i{}e", v)?;
+        Ok(())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        write!(self.output, "i{}e", v)?;
+        Ok(())
     }
 
     fn serialize_f32(self, _v: f32) -> Result<()> {
@@ -88,9 +161,12 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<()> {
-        self.output.extend(v.len().to_string().as_bytes());
-        self.output.push(b':');
-        self.output.extend(v);
+        if self.raw {
+            self.output.write_all(v)?;
+            return Ok(());
+        }
+        write!(self.output, "{}:", v.len())?;
+        self.output.write_all(v)?;
         Ok(())
     }
 
@@ -114,16 +190,22 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<()> {
-        Err(Error::Message("bencode does not support enums".into()))
+        self.serialize_str(variant)
     }
 
     fn serialize_newtype_struct<T: ?Sized + Serialize>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<()> {
+        if name == raw::TOKEN {
+            self.raw = true;
+            let result = value.serialize(&mut *self);
+            self.raw = false;
+            return result;
+        }
         value.serialize(self)
     }
 
@@ -131,14 +213,19 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _value: &T,
+        variant: &'static str,
+        value: &T,
     ) -> Result<()> {
-        Err(Error::Message("bencode does not support enums".into()))
+        // Externally tagged: a dict with the variant name as its only key
+        self.output.write_all(b"d")?;
+        self.serialize_str(variant)?;
+        self.serialize_element(value)?;
+        self.output.write_all(b"e")?;
+        Ok(())
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        self.output.push(b'l');
+        self.output.write_all(b"l")?;
         Ok(self)
     }
 
@@ -158,10 +245,13 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        Err(Error::Message("bencode does not support enums".into()))
+        self.output.write_all(b"d")?;
+        self.serialize_str(variant)?;
+        self.output.write_all(b"l")?;
+        Ok(self)
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
@@ -176,110 +266,108 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        Err(Error::Message("bencode does not support enums".into()))
+        self.output.write_all(b"d")?;
+        self.serialize_str(variant)?;
+        let mut map = SortedMapSerializer::new(self);
+        map.variant = true;
+        Ok(map)
     }
 }
 
-impl<'a> ser::SerializeSeq for &'a mut Serializer {
+impl<W: Write> ser::SerializeSeq for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
     fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
-        value.serialize(&mut **self)
+        Serializer::serialize_element(self, value)
     }
 
     fn end(self) -> Result<()> {
-        self.output.push(b'e');
+        self.output.write_all(b"e")?;
         Ok(())
     }
 }
 
-impl<'a> ser::SerializeTuple for &'a mut Serializer {
+impl<W: Write> ser::SerializeTuple for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
     fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
-        value.serialize(&mut **self)
+        Serializer::serialize_element(self, value)
     }
 
     fn end(self) -> Result<()> {
-        self.output.push(b'e');
+        self.output.write_all(b"e")?;
         Ok(())
     }
 }
 
-impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
+impl<W: Write> ser::SerializeTupleStruct for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
     fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
-        value.serialize(&mut **self)
+        Serializer::serialize_element(self, value)
     }
 
     fn end(self) -> Result<()> {
-        self.output.push(b'e');
+        self.output.write_all(b"e")?;
         Ok(())
     }
 }
 
-impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
-    type Ok = ();
-    type Error = Error;
-
-    fn serialize_field<T: ?Sized + Serialize>(&mut self, _value: &T) -> Result<()> {
-        Err(Error::Message("bencode does not support enums".into()))
-    }
-
-    fn end(self) -> Result<()> {
-        Err(Error::Message("bencode does not support enums".into()))
-    }
-}
-
-impl<'a> ser::SerializeStructVariant for &'a mut Serializer {
+impl<W: Write> ser::SerializeTupleVariant for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized + Serialize>(
-        &mut self,
-        _key: &'static str,
-        _value: &T,
-    ) -> Result<()> {
-        Err(Error::Message("bencode does not support enums".into()))
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        Serializer::serialize_element(self, value)
     }
 
     fn end(self) -> Result<()> {
-        Err(Error::Message("bencode does not support enums".into()))
+        // Close the list, then the enclosing single-key dict
+        self.output.write_all(b"ee")?;
+        Ok(())
     }
 }
 
 /// A serializer that collects map entries and sorts them by key
-pub struct SortedMapSerializer<'a> {
-    ser: &'a mut Serializer,
-    entries: Vec<(Vec<u8>, Vec<u8>)>,
+///
+/// Dictionary keys must be written in sorted order, so the values are encoded
+/// into one buffer until the whole map is known; everything else streams
+/// straight through.
+pub struct SortedMapSerializer<'a, W> {
+    ser: &'a mut Serializer<W>,
+    /// Raw key bytes (without the length prefix) and where their value sits in `buffer`
+    entries: Vec<(Vec<u8>, Range<usize>)>,
+    /// Encoded values, back to back
+    buffer: Vec<u8>,
     current_key: Option<Vec<u8>>,
+    /// Whether this map is the body of a struct variant and must close its wrapper dict
+    variant: bool,
 }
 
-impl<'a> SortedMapSerializer<'a> {
-    fn new(ser: &'a mut Serializer) -> Self {
+impl<'a, W: Write> SortedMapSerializer<'a, W> {
+    fn new(ser: &'a mut Serializer<W>) -> Self {
         SortedMapSerializer {
             ser,
             entries: Vec::new(),
+            buffer: Vec::new(),
             current_key: None,
+            variant: false,
         }
     }
 }
 
-impl<'a> ser::SerializeMap for SortedMapSerializer<'a> {
+impl<W: Write> ser::SerializeMap for SortedMapSerializer<'_, W> {
     type Ok = ();
     type Error = Error;
 
     fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
-        let mut key_serializer = Serializer::new();
-        key.serialize(&mut key_serializer)?;
-        self.current_key = Some(key_serializer.output);
+        self.current_key = Some(key.serialize(KeySerializer)?);
         Ok(())
     }
 
@@ -288,26 +376,58 @@ impl<'a> ser::SerializeMap for SortedMapSerializer<'a> {
             .current_key
             .take()
             .ok_or_else(|| Error::Message("serialize_value called before serialize_key".into()))?;
-        let mut value_serializer = Serializer::new();
-        value.serialize(&mut value_serializer)?;
-        self.entries.push((key, value_serializer.output));
+        // `None` and unit encode to nothing; leave the entry out rather than
+        // writing a key without a value
+        let start = self.buffer.len();
+        value.serialize(&mut Serializer::new(&mut self.buffer))?;
+        if self.buffer.len() > start {
+            self.entries.push((key, start..self.buffer.len()));
+        }
         Ok(())
     }
 
     fn end(self) -> Result<()> {
-        self.ser.output.push(b'd');
+        // Canonical order is by the raw key bytes, not their encoded form
         let mut entries = self.entries;
         entries.sort_by(|a, b| a.0.cmp(&b.0));
+        if entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err(Error::DuplicateDictKey);
+        }
+
+        let output = &mut self.ser.output;
+        output.write_all(b"d")?;
         for (key, value) in entries {
-            self.ser.output.extend(key);
-            self.ser.output.extend(value);
+            write!(output, "{}:", key.len())?;
+            output.write_all(&key)?;
+            output.write_all(&self.buffer[value])?;
+        }
+        output.write_all(b"e")?;
+        if self.variant {
+            output.write_all(b"e")?;
         }
-        self.ser.output.push(b'e');
         Ok(())
     }
 }
 
-impl<'a> ser::SerializeStruct for SortedMapSerializer<'a> {
+impl<W: Write> ser::SerializeStruct for SortedMapSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        ser::SerializeMap::serialize_key(self, key)?;
+        ser::SerializeMap::serialize_value(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for SortedMapSerializer<'_, W> {
     type Ok = ();
     type Error = Error;
 
@@ -325,10 +445,227 @@ impl<'a> ser::SerializeStruct for SortedMapSerializer<'a> {
     }
 }
 
+/// Serializes a dictionary key to its raw bytes, rejecting anything but strings
+struct KeySerializer;
+
+impl ser::Serializer for KeySerializer {
+    type Ok = Vec<u8>;
+    type Error = Error;
+
+    type SerializeSeq = ser::Impossible<Vec<u8>, Error>;
+    type SerializeTuple = ser::Impossible<Vec<u8>, Error>;
+    type SerializeTupleStruct = ser::Impossible<Vec<u8>, Error>;
+    type SerializeTupleVariant = ser::Impossible<Vec<u8>, Error>;
+    type SerializeMap = ser::Impossible<Vec<u8>, Error>;
+    type SerializeStruct = ser::Impossible<Vec<u8>, Error>;
+    type SerializeStructVariant = ser::Impossible<Vec<u8>, Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Vec<u8>> {
+        self.serialize_str(&v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Vec<u8>> {
+        Ok(v.as_bytes().to_vec())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Vec<u8>> {
+        Ok(v.to_vec())
+    }
+
+    fn serialize_none(self) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_unit(self) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Vec<u8>> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Vec<u8>> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(Error::InvalidDictKey)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Error::InvalidDictKey)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
     use super::*;
-    use serde::Serialize;
+    use serde::{Deserialize, Serialize};
+
+    #[test]
+    fn none_fields_are_left_out() {
+        #[derive(Serialize)]
+        struct Torrent {
+            announce: Option<String>,
+            comment: Option<String>,
+            #[serde(rename = "creation date")]
+            creation_date: Option<i64>,
+            info: BTreeMap<String, Option<i64>>,
+        }
+
+        let torrent = Torrent {
+            announce: Some("url".into()),
+            comment: None,
+            creation_date: Some(1),
+            info: BTreeMap::from([("length".into(), Some(42)), ("private".into(), None)]),
+        };
+        assert_eq!(
+            to_bytes(&torrent).unwrap(),
+            b"d8:announce3:url13:creation datei1e4:infod6:lengthi42eee"
+        );
+    }
+
+    #[test]
+    fn flatten_round_trip() {
+        use crate::de::from_bytes;
+        use crate::value::Value;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Info {
+            name: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            private: Option<u8>,
+            #[serde(flatten)]
+            extra: BTreeMap<String, Value>,
+        }
+
+        let input = b"d6:lengthi42e4:name3:abc6:source3:xyze";
+        let info: Info = from_bytes(input).unwrap();
+        assert_eq!(info.private, None);
+        assert_eq!(to_bytes(&info).unwrap(), input);
+    }
+
+    #[test]
+    fn serialize_wide_integers() {
+        assert_eq!(to_bytes(&u64::MAX).unwrap(), b"i18446744073709551615e");
+        assert_eq!(
+            to_bytes(&i128::MIN).unwrap(),
+            b"i-170141183460469231731687303715884105728e"
+        );
+        assert_eq!(
+            to_bytes(&u128::MAX).unwrap(),
+            b"i340282366920938463463374607431768211455e"
+        );
+    }
 
     #[test]
     fn serialize_integer() {
@@ -376,4 +713,191 @@ mod tests {
         // Keys should be sorted: "apple" before "zebra"
         assert_eq!(to_bytes(&d).unwrap(), b"d5:applei2e5:zebrai1ee");
     }
+
+    #[test]
+    fn serialize_to_writer() {
+        #[derive(Serialize)]
+        struct Data {
+            name: String,
+            sizes: Vec<i64>,
+        }
+
+        let d = Data {
+            name: "spam".into(),
+            sizes: vec![1, 2],
+        };
+        let mut output = Vec::new();
+        to_writer(&mut output, &d).unwrap();
+        assert_eq!(output, to_bytes(&d).unwrap());
+    }
+
+    #[test]
+    fn serialize_to_writer_reports_io_errors() {
+        let mut buffer = [0u8; 4];
+        let result = to_writer(&mut buffer[..], &vec![1i64, 2, 3]);
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn serialize_keys_in_raw_byte_order() {
+        #[derive(Serialize)]
+        struct Metainfo {
+            info: i64,
+            announce: String,
+            #[serde(rename = "announce-list")]
+            announce_list: Vec<Vec<String>>,
+            #[serde(rename = "creation date")]
+            creation_date: i64,
+        }
+
+        let m = Metainfo {
+            info: 1,
+            announce: "a".into(),
+            announce_list: vec![],
+            creation_date: 2,
+        };
+        assert_eq!(
+            to_bytes(&m).unwrap(),
+            b"d8:announce1:a13:announce-listle13:creation datei2e4:infoi1ee"
+        );
+    }
+
+    #[test]
+    fn serialize_map_keys_of_different_lengths() {
+        let mut map = HashMap::new();
+        map.insert("2", 2i64);
+        map.insert("10", 10);
+        map.insert("b", 0);
+        map.insert("aa", 0);
+        assert_eq!(to_bytes(&map).unwrap(), b"d2:10i10e1:2i2e2:aai0e1:bi0ee");
+    }
+
+    #[test]
+    fn reject_non_string_keys() {
+        let mut map = BTreeMap::new();
+        map.insert(1i64, 2i64);
+        assert!(matches!(to_bytes(&map), Err(Error::InvalidDictKey)));
+
+        let mut map = BTreeMap::new();
+        map.insert(vec![1u8], 2i64);
+        assert!(matches!(to_bytes(&map), Err(Error::InvalidDictKey)));
+    }
+
+    #[test]
+    fn reject_duplicate_keys() {
+        #[derive(Serialize)]
+        struct Dup {
+            name: String,
+            #[serde(flatten)]
+            extra: BTreeMap<String, i64>,
+        }
+
+        let mut extra = BTreeMap::new();
+        extra.insert("name".to_string(), 1);
+        let d = Dup {
+            name: "x".into(),
+            extra,
+        };
+        assert!(matches!(to_bytes(&d), Err(Error::DuplicateDictKey)));
+    }
+
+    #[test]
+    fn output_decodes_under_strict_mode() {
+        #[derive(Serialize)]
+        struct File {
+            length: i64,
+            path: Vec<String>,
+        }
+
+        #[derive(Serialize)]
+        struct Info {
+            name: String,
+            #[serde(rename = "piece length")]
+            piece_length: i64,
+            files: Vec<File>,
+            #[serde(flatten)]
+            extra: HashMap<String, i64>,
+        }
+
+        let mut extra = HashMap::new();
+        for key in ["private", "x", "zz", "10", "9", "a-b", "A"] {
+            extra.insert(key.to_string(), 1);
+        }
+        let info = Info {
+            name: "data".into(),
+            piece_length: 16384,
+            files: vec![File {
+                length: 3,
+                path: vec!["a".into()],
+            }],
+            extra,
+        };
+
+        let bytes = to_bytes(&info).unwrap();
+        let value = crate::decode_with_options(&bytes, crate::DecoderOptions::strict()).unwrap();
+        assert_eq!(to_bytes(&value).unwrap(), bytes);
+    }
+
+    #[test]
+    fn serialize_enums_externally_tagged() {
+        #[derive(Serialize)]
+        enum Message {
+            Choke,
+            Have(u32),
+            Request(u32, u32, u32),
+            Extended { id: u8, payload: String },
+        }
+
+        assert_eq!(to_bytes(&Message::Choke).unwrap(), b"5:Choke");
+        assert_eq!(to_bytes(&Message::Have(7)).unwrap(), b"d4:Havei7ee");
+        assert_eq!(
+            to_bytes(&Message::Request(1, 2, 3)).unwrap(),
+            b"d7:Requestli1ei2ei3eee"
+        );
+        assert_eq!(
+            to_bytes(&Message::Extended {
+                payload: "x".into(),
+                id: 1
+            })
+            .unwrap(),
+            b"d8:Extendedd2:idi1e7:payload1:xee"
+        );
+    }
+
+    #[test]
+    fn reject_missing_values_in_variants_and_lists() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum E {
+            A(Option<u32>),
+            B(()),
+            C(u32, Option<u32>),
+        }
+
+        let bytes = to_bytes(&E::A(Some(1))).unwrap();
+        assert_eq!(bytes, b"d1:Ai1ee");
+        assert_eq!(crate::from_bytes::<E>(&bytes).unwrap(), E::A(Some(1)));
+
+        for value in [E::A(None), E::B(()), E::C(1, None)] {
+            assert!(to_bytes(&value).is_err(), "{:?}", value);
+            assert!(crate::to_value(&value).is_err(), "{:?}", value);
+        }
+
+        // Dropping the `None` would silently change the arity
+        assert!(to_bytes(&vec![Some(1), None, Some(3)]).is_err());
+        assert!(to_bytes(&(1, None::<u32>)).is_err());
+        assert!(crate::to_value(&vec![Some(1), None]).is_err());
+    }
+
+    #[test]
+    fn serialize_internally_tagged_enum() {
+        #[derive(Serialize)]
+        #[serde(tag = "y")]
+        enum Krpc {
+            #[serde(rename = "q")]
+            Query { q: String },
+        }
+
+        let msg = Krpc::Query { q: "ping".into() };
+        assert_eq!(to_bytes(&msg).unwrap(), b"d1:q4:ping1:y1:qe");
+    }
 }
@@ -1,10 +1,23 @@
 use std::collections::BTreeMap;
 
+use crate::error::{Error, Result};
+use crate::integer::BigInteger;
+
+mod de;
+mod index;
+mod ser;
+
+pub use self::de::from_value;
+pub use self::index::ValueIndex;
+pub use self::ser::to_value;
+
 /// Represents a bencode value
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Value {
     /// Integer: i<number>e (e.g., i42e)
     Integer(i64),
+    /// Integer outside the `i64` range, only decoded if enabled in the options
+    BigInteger(BigInteger),
     /// Byte string: <length>:<data> (e.g., 4:spam)
     ByteString(Vec<u8>),
     /// List: l<items>e (e.g., li1ei2ee)
@@ -12,3 +25,227 @@ pub enum Value {
     /// Dictionary: d<pairs>e - keys must be sorted byte strings
     Dict(BTreeMap<Vec<u8>, Value>),
 }
+
+impl Value {
+    /// The integer, if this is one
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Integer(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// The integer, if this is one too large for `i64`
+    pub fn as_big_integer(&self) -> Option<&BigInteger> {
+        match self {
+            Value::BigInteger(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// The raw bytes, if this is a byte string
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::ByteString(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// The text, if this is a byte string holding valid UTF-8
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(self.as_bytes()?).ok()
+    }
+
+    /// The items, if this is a list
+    pub fn as_list(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Mutable access to the items, if this is a list
+    pub fn as_list_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// The entries, if this is a dictionary
+    pub fn as_dict(&self) -> Option<&BTreeMap<Vec<u8>, Value>> {
+        match self {
+            Value::Dict(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Mutable access to the entries, if this is a dictionary
+    pub fn as_dict_mut(&mut self) -> Option<&mut BTreeMap<Vec<u8>, Value>> {
+        match self {
+            Value::Dict(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Insert a dictionary entry, returning the value it replaced
+    ///
+    /// Fails, leaving the value untouched, if this is not a dictionary.
+    pub fn insert(&mut self, key: impl Into<Vec<u8>>, value: Value) -> Result<Option<Value>> {
+        let map = self
+            .as_dict_mut()
+            .ok_or_else(|| Error::Message("bencode value is not a dictionary".into()))?;
+        Ok(map.insert(key.into(), value))
+    }
+
+    /// Remove a dictionary entry; `None` if it is missing or this is not a dictionary
+    pub fn remove(&mut self, key: impl AsRef<[u8]>) -> Option<Value> {
+        self.as_dict_mut()?.remove(key.as_ref())
+    }
+}
+
+macro_rules! from_integer {
+    ($($ty:ty)*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(n: $ty) -> Self {
+                    Value::Integer(i64::from(n))
+                }
+            }
+        )*
+    };
+}
+
+from_integer!(i8 i16 i32 i64 u8 u16 u32);
+
+macro_rules! from_wide_integer {
+    ($($ty:ty)*) => {
+        $(
+            /// Integers outside the `i64` range become [`Value::BigInteger`]
+            impl From<$ty> for Value {
+                fn from(n: $ty) -> Self {
+                    match i64::try_from(n) {
+                        Ok(n) => Value::Integer(n),
+                        Err(_) => Value::BigInteger(
+                            n.to_string().parse().expect("integer is outside i64"),
+                        ),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_wide_integer!(u64 i128 u128);
+
+impl From<BigInteger> for Value {
+    fn from(n: BigInteger) -> Self {
+        Value::BigInteger(n)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::ByteString(s.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::ByteString(s.into_bytes())
+    }
+}
+
+impl From<&[u8]> for Value {
+    fn from(bytes: &[u8]) -> Self {
+        Value::ByteString(bytes.to_vec())
+    }
+}
+
+impl<const N: usize> From<&[u8; N]> for Value {
+    fn from(bytes: &[u8; N]) -> Self {
+        Value::ByteString(bytes.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(bytes: Vec<u8>) -> Self {
+        Value::ByteString(bytes)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(items: Vec<Value>) -> Self {
+        Value::List(items)
+    }
+}
+
+impl From<BTreeMap<Vec<u8>, Value>> for Value {
+    fn from(map: BTreeMap<Vec<u8>, Value>) -> Self {
+        Value::Dict(map)
+    }
+}
+
+/// A bencode value borrowing its byte strings from the input buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueRef<'a> {
+    /// Integer: i<number>e (e.g., i42e)
+    Integer(i64),
+    /// Integer outside the `i64` range, only decoded if enabled in the options
+    BigInteger(BigInteger),
+    /// Byte string: <length>:<data> (e.g., 4:spam)
+    ByteString(&'a [u8]),
+    /// List: l<items>e (e.g., li1ei2ee)
+    List(Vec<ValueRef<'a>>),
+    /// Dictionary: d<pairs>e - keys must be sorted byte strings
+    Dict(BTreeMap<&'a [u8], ValueRef<'a>>),
+}
+
+impl ValueRef<'_> {
+    /// Copy into an owned Value
+    pub fn to_value(&self) -> Value {
+        match self {
+            ValueRef::Integer(n) => Value::Integer(*n),
+            ValueRef::BigInteger(n) => Value::BigInteger(n.clone()),
+            ValueRef::ByteString(bytes) => Value::ByteString(bytes.to_vec()),
+            ValueRef::List(items) => Value::List(items.iter().map(ValueRef::to_value).collect()),
+            ValueRef::Dict(map) => Value::Dict(
+                map.iter()
+                    .map(|(key, value)| (key.to_vec(), value.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<ValueRef<'_>> for Value {
+    fn from(value: ValueRef<'_>) -> Self {
+        match value {
+            ValueRef::Integer(n) => Value::Integer(n),
+            ValueRef::BigInteger(n) => Value::BigInteger(n),
+            ValueRef::ByteString(bytes) => Value::ByteString(bytes.to_vec()),
+            ValueRef::List(items) => Value::List(items.into_iter().map(Value::from).collect()),
+            ValueRef::Dict(map) => Value::Dict(
+                map.into_iter()
+                    .map(|(key, value)| (key.to_vec(), Value::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl<'a> From<&'a Value> for ValueRef<'a> {
+    fn from(value: &'a Value) -> Self {
+        match value {
+            Value::Integer(n) => ValueRef::Integer(*n),
+            Value::BigInteger(n) => ValueRef::BigInteger(n.clone()),
+            Value::ByteString(bytes) => ValueRef::ByteString(bytes),
+            Value::List(items) => ValueRef::List(items.iter().map(ValueRef::from).collect()),
+            Value::Dict(map) => ValueRef::Dict(
+                map.iter()
+                    .map(|(key, value)| (key.as_slice(), ValueRef::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
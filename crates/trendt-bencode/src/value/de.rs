@@ -0,0 +1,406 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+use super::Value;
+use crate::encode::encode;
+use crate::error::{Error, Result};
+use crate::integer::out_of_range;
+use crate::raw;
+
+impl<'de> de::Deserialize<'de> for Value {
+    fn deserialize<D: de::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a bencode value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> std::result::Result<Value, E> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<Value, E> {
+        Ok(Value::Integer(v))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> std::result::Result<Value, E> {
+        Ok(Value::from(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<Value, E> {
+        Ok(Value::from(v))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> std::result::Result<Value, E> {
+        Ok(Value::from(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Value, E> {
+        Ok(Value::ByteString(v.as_bytes().to_vec()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> std::result::Result<Value, E> {
+        Ok(Value::ByteString(v.into_bytes()))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> std::result::Result<Value, E> {
+        Ok(Value::ByteString(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> std::result::Result<Value, E> {
+        Ok(Value::ByteString(v))
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_newtype_struct<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Value, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::List(items))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> std::result::Result<Value, A::Error> {
+        let mut dict = BTreeMap::new();
+        while let Some((Key(key), value)) = map.next_entry()? {
+            dict.insert(key, value);
+        }
+        Ok(Value::Dict(dict))
+    }
+}
+
+/// A dictionary key, accepted as either a byte string or a text string
+struct Key(Vec<u8>);
+
+impl<'de> de::Deserialize<'de> for Key {
+    fn deserialize<D: de::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        struct KeyVisitor;
+
+        impl Visitor<'_> for KeyVisitor {
+            type Value = Key;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a byte string key")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Key, E> {
+                Ok(Key(v.as_bytes().to_vec()))
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> std::result::Result<Key, E> {
+                Ok(Key(v.to_vec()))
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> std::result::Result<Key, E> {
+                Ok(Key(v))
+            }
+        }
+
+        deserializer.deserialize_bytes(KeyVisitor)
+    }
+}
+
+/// Convert a Value into any deserializable type
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T> {
+    T::deserialize(value)
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Value::Integer(n) => visitor.visit_i64(n),
+            Value::BigInteger(n) => {
+                if let Ok(n) = u64::try_from(&n) {
+                    visitor.visit_u64(n)
+                } else if let Ok(n) = i128::try_from(&n) {
+                    visitor.visit_i128(n)
+                } else if let Ok(n) = u128::try_from(&n) {
+                    visitor.visit_u128(n)
+                } else {
+                    Err(out_of_range(n.as_str(), "128-bit integer"))
+                }
+            }
+            Value::ByteString(bytes) => visitor.visit_byte_buf(bytes),
+            Value::List(items) => {
+                let mut seq = de::value::SeqDeserializer::new(items.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Dict(map) => {
+                let entries = map
+                    .into_iter()
+                    .map(|(key, value)| (Value::ByteString(key), value));
+                let mut map = de::value::MapDeserializer::new(entries);
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Value::Integer(n) => visitor.visit_bool(n != 0),
+            other => other.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Value::ByteString(bytes) => match String::from_utf8(bytes) {
+                Ok(s) => visitor.visit_string(s),
+                Err(err) => visitor.visit_byte_buf(err.into_bytes()),
+            },
+            other => other.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        if name == raw::TOKEN {
+            return visitor.visit_byte_buf(encode(&self));
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let (variant, content) = match self {
+            // Unit variant: just the variant name
+            Value::ByteString(name) => (name, None),
+            // Any other variant: a dict with the variant name as its only key
+            Value::Dict(map) if map.len() == 1 => {
+                let (name, content) = map.into_iter().next().expect("map has one entry");
+                (name, Some(content))
+            }
+            _ => {
+                return Err(Error::Message(
+                    "expected a byte string or single-key dict for enum".into(),
+                ));
+            }
+        };
+        visitor.visit_enum(EnumDeserializer { variant, content })
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct ignored_any
+    }
+}
+
+struct EnumDeserializer {
+    variant: Vec<u8>,
+    content: Option<Value>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, VariantDeserializer)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(Value::ByteString(self.variant))?;
+        Ok((
+            variant,
+            VariantDeserializer {
+                content: self.content,
+            },
+        ))
+    }
+}
+
+struct VariantDeserializer {
+    content: Option<Value>,
+}
+
+impl VariantDeserializer {
+    fn content(self, expected: &str) -> Result<Value> {
+        self.content
+            .ok_or_else(|| de::Error::invalid_type(de::Unexpected::UnitVariant, &expected))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.content("newtype variant")?)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self.content("tuple variant")?, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self.content("struct variant")?, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{RawBencode, decode, from_bytes, to_bytes, to_value};
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Info {
+        name: String,
+        #[serde(rename = "piece length")]
+        piece_length: i64,
+        #[serde(flatten)]
+        extra: BTreeMap<String, Value>,
+    }
+
+    #[test]
+    fn deserialize_value_from_bytes() {
+        let input = b"d3:bar4:spam3:fooli1ei-2eee";
+        let value: Value = from_bytes(input).unwrap();
+        assert_eq!(value, decode(input).unwrap());
+    }
+
+    #[test]
+    fn value_to_struct() {
+        let value = decode(b"d4:name3:abc12:piece lengthi16384e7:privatei1ee").unwrap();
+        let info: Info = from_value(value).unwrap();
+        assert_eq!(info.name, "abc");
+        assert_eq!(info.piece_length, 16384);
+        assert_eq!(info.extra.get("private"), Some(&Value::Integer(1)));
+    }
+
+    #[test]
+    fn unknown_keys_survive_round_trip() {
+        let input = b"d4:name3:abc12:piece lengthi16384e7:privatei1e6:sourcel1:xee";
+        let info: Info = from_bytes(input).unwrap();
+        assert_eq!(info.extra.len(), 2);
+        assert_eq!(
+            to_bytes(&info).unwrap(),
+            to_bytes(&decode(input).unwrap()).unwrap()
+        );
+
+        let value = to_value(&info).unwrap();
+        assert_eq!(from_value::<Info>(value).unwrap(), info);
+    }
+
+    #[test]
+    fn value_to_raw() {
+        #[derive(Deserialize)]
+        struct Metainfo<'a> {
+            #[serde(borrow)]
+            info: RawBencode<'a>,
+        }
+
+        let value = decode(b"d4:infod4:name3:abcee").unwrap();
+        let m = Metainfo::deserialize(value).unwrap();
+        assert_eq!(m.info.as_bytes(), b"d4:name3:abce");
+    }
+
+    #[test]
+    fn from_value_reports_type_errors() {
+        assert!(from_value::<u8>(Value::Integer(256)).is_err());
+        assert!(from_value::<String>(Value::ByteString(vec![0xff])).is_err());
+        assert!(from_value::<Vec<i64>>(Value::Integer(1)).is_err());
+    }
+
+    #[test]
+    fn value_to_enum() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum Message {
+            Choke,
+            Have(u32),
+            Request(u32, u32, u32),
+            Extended { id: u8, payload: String },
+        }
+
+        let cases = [
+            (&b"5:Choke"[..], Message::Choke),
+            (b"d4:Havei7ee", Message::Have(7)),
+            (b"d7:Requestli1ei2ei3eee", Message::Request(1, 2, 3)),
+            (
+                b"d8:Extendedd2:idi1e7:payload1:xee",
+                Message::Extended {
+                    id: 1,
+                    payload: "x".into(),
+                },
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                from_value::<Message>(decode(input).unwrap()).unwrap(),
+                expected
+            );
+        }
+        assert!(from_value::<Message>(decode(b"d5:Chokele4:Havei7ee").unwrap()).is_err());
+        assert!(from_value::<Message>(decode(b"d4:Havelee").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+use std::ops;
+
+use super::Value;
+
+/// A type that can index into a [`Value`]
+///
+/// Strings and byte strings look up dictionary keys and `usize` looks up list
+/// items. Used by [`Value::get`], [`Value::get_mut`] and `value[...]`.
+pub trait ValueIndex: private::Sealed {
+    #[doc(hidden)]
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value>;
+
+    #[doc(hidden)]
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value>;
+}
+
+impl ValueIndex for usize {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        value.as_list()?.get(*self)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        value.as_list_mut()?.get_mut(*self)
+    }
+}
+
+impl ValueIndex for [u8] {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        value.as_dict()?.get(self)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        value.as_dict_mut()?.get_mut(self)
+    }
+}
+
+impl<const N: usize> ValueIndex for [u8; N] {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        self[..].index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        self[..].index_into_mut(value)
+    }
+}
+
+impl ValueIndex for Vec<u8> {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        self[..].index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        self[..].index_into_mut(value)
+    }
+}
+
+impl ValueIndex for str {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        self.as_bytes().index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        self.as_bytes().index_into_mut(value)
+    }
+}
+
+impl ValueIndex for String {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        self.as_bytes().index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        self.as_bytes().index_into_mut(value)
+    }
+}
+
+impl<T: ?Sized + ValueIndex> ValueIndex for &T {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        (**self).index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        (**self).index_into_mut(value)
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for usize {}
+    impl Sealed for [u8] {}
+    impl<const N: usize> Sealed for [u8; N] {}
+    impl Sealed for Vec<u8> {}
+    impl Sealed for str {}
+    impl Sealed for String {}
+    impl<T: ?Sized + Sealed> Sealed for &T {}
+}
+
+/// Panics if the key or index is missing, like indexing a map or slice
+impl<I: ValueIndex> ops::Index<I> for Value {
+    type Output = Value;
+
+    fn index(&self, index: I) -> &Value {
+        index
+            .index_into(self)
+            .expect("no such key or index in bencode value")
+    }
+}
+
+/// Panics if the key or index is missing; use [`Value::insert`] to add keys
+impl<I: ValueIndex> ops::IndexMut<I> for Value {
+    fn index_mut(&mut self, index: I) -> &mut Value {
+        index
+            .index_into_mut(self)
+            .expect("no such key or index in bencode value")
+    }
+}
+
+/// Split a JSON-pointer style path into unescaped segments
+///
+/// Returns `None` unless the path is empty or starts with `/`.
+fn segments(pointer: &str) -> Option<impl Iterator<Item = String> + '_> {
+    if !pointer.is_empty() && !pointer.starts_with('/') {
+        return None;
+    }
+    Some(
+        pointer
+            .split('/')
+            .skip(1)
+            .map(|segment| segment.replace("~1", "/").replace("~0", "~")),
+    )
+}
+
+impl Value {
+    /// Look up a dictionary key or list index
+    pub fn get<I: ValueIndex>(&self, index: I) -> Option<&Value> {
+        index.index_into(self)
+    }
+
+    /// Mutable version of [`get`](Value::get)
+    pub fn get_mut<I: ValueIndex>(&mut self, index: I) -> Option<&mut Value> {
+        index.index_into_mut(self)
+    }
+
+    /// Look up a nested value by a JSON-pointer style path
+    ///
+    /// `value.pointer("/info/files/0/length")` walks dictionary keys and list
+    /// indices; `~1` and `~0` escape `/` and `~` inside keys. The empty path
+    /// refers to the value itself.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        segments(pointer)?.try_fold(self, |value, segment| match value {
+            Value::List(_) => value.get(segment.parse::<usize>().ok()?),
+            _ => value.get(segment.as_str()),
+        })
+    }
+
+    /// Mutable version of [`pointer`](Value::pointer)
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        segments(pointer)?.try_fold(self, |value, segment| match value {
+            Value::List(_) => value.get_mut(segment.parse::<usize>().ok()?),
+            _ => value.get_mut(segment.as_str()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decode::decode;
+    use crate::value::Value;
+
+    fn torrent() -> Value {
+        decode(b"d8:announce3:url4:infod5:filesld6:lengthi1e4:pathl1:a1:beed6:lengthi2e4:pathl1:ceee4:name3:abcee")
+            .unwrap()
+    }
+
+    #[test]
+    fn get_and_index() {
+        let value = torrent();
+        assert_eq!(value.get("announce").and_then(Value::as_str), Some("url"));
+        assert_eq!(value.get(b"announce"), value.get("announce"));
+        assert_eq!(value["info"]["files"][1]["length"].as_int(), Some(2));
+        assert!(value.get("missing").is_none());
+        assert!(value.get(0).is_none());
+        assert!(value["announce"].get("x").is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "no such key")]
+    fn index_missing_key_panics() {
+        let _ = &torrent()["missing"];
+    }
+
+    #[test]
+    fn pointer() {
+        let value = torrent();
+        assert_eq!(value.pointer(""), Some(&value));
+        assert_eq!(
+            value
+                .pointer("/info/files/0/path/1")
+                .and_then(Value::as_str),
+            Some("b")
+        );
+        assert_eq!(
+            value.pointer("/info/name").and_then(Value::as_str),
+            Some("abc")
+        );
+        assert!(value.pointer("/info/files/2").is_none());
+        assert!(value.pointer("/info/files/x").is_none());
+        assert!(value.pointer("info").is_none());
+
+        let value = decode(b"d3:a/bi1e3:c~di2ee").unwrap();
+        assert_eq!(value.pointer("/a~1b").and_then(Value::as_int), Some(1));
+        assert_eq!(value.pointer("/c~0d").and_then(Value::as_int), Some(2));
+    }
+
+    #[test]
+    fn patch_in_place() {
+        let mut value = torrent();
+        value["announce"] = Value::ByteString(b"http://tracker".to_vec());
+        *value.pointer_mut("/info/files/1/length").unwrap() = Value::Integer(3);
+        value["info"]["files"][0]["path"]
+            .as_list_mut()
+            .unwrap()
+            .push(Value::ByteString(b"z".to_vec()));
+        assert_eq!(value.insert("url-list", Value::List(vec![])).unwrap(), None);
+        assert!(value.remove("missing").is_none());
+
+        assert_eq!(value["announce"].as_str(), Some("http://tracker"));
+        assert_eq!(
+            value.pointer("/info/files/1/length").unwrap().as_int(),
+            Some(3)
+        );
+        assert_eq!(
+            value.pointer("/info/files/0/path/2").unwrap().as_str(),
+            Some("z")
+        );
+        assert_eq!(value["url-list"], Value::List(vec![]));
+    }
+
+    #[test]
+    fn insert_into_non_dict() {
+        let mut value = Value::List(vec![]);
+        assert!(value.insert("key", Value::Integer(1)).is_err());
+        assert_eq!(value, Value::List(vec![]));
+        assert!(value.remove("key").is_none());
+    }
+}
@@ -0,0 +1,504 @@
+use std::collections::BTreeMap;
+
+use serde::ser::{self, Serialize};
+
+use super::{Value, ValueRef};
+use crate::decode::decode_with_options;
+use crate::error::{Error, Result};
+use crate::integer::BigInteger;
+use crate::options::DecoderOptions;
+use crate::raw;
+use crate::ser::missing_value;
+
+impl Serialize for Value {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Value::Integer(n) => serializer.serialize_i64(*n),
+            Value::BigInteger(n) => serialize_big_integer(n, serializer),
+            Value::ByteString(bytes) => serializer.serialize_bytes(bytes),
+            Value::List(items) => serializer.collect_seq(items),
+            Value::Dict(map) => {
+                serializer.collect_map(map.iter().map(|(key, value)| (Bytes(key), value)))
+            }
+        }
+    }
+}
+
+impl Serialize for ValueRef<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            ValueRef::Integer(n) => serializer.serialize_i64(*n),
+            ValueRef::BigInteger(n) => serialize_big_integer(n, serializer),
+            ValueRef::ByteString(bytes) => serializer.serialize_bytes(bytes),
+            ValueRef::List(items) => serializer.collect_seq(items),
+            ValueRef::Dict(map) => {
+                serializer.collect_map(map.iter().map(|(key, value)| (Bytes(key), value)))
+            }
+        }
+    }
+}
+
+/// Serialize a big integer through the narrowest serde type that holds it
+fn serialize_big_integer<S: ser::Serializer>(
+    n: &BigInteger,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    if let Ok(n) = u64::try_from(n) {
+        serializer.serialize_u64(n)
+    } else if let Ok(n) = i128::try_from(n) {
+        serializer.serialize_i128(n)
+    } else if let Ok(n) = u128::try_from(n) {
+        serializer.serialize_u128(n)
+    } else {
+        // Too wide for serde's integer types: pass the encoding through raw
+        let encoded = format!("i{}e", n);
+        serializer.serialize_newtype_struct(raw::TOKEN, &Bytes(encoded.as_bytes()))
+    }
+}
+
+/// Serializes a slice as a byte string rather than a sequence
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// Convert any serializable type into a Value
+pub fn to_value<T: ?Sized + Serialize>(value: &T) -> Result<Value> {
+    value.serialize(ValueSerializer)?.ok_or_else(missing_value)
+}
+
+/// A serializer producing a Value tree
+///
+/// `None` and unit produce no value at all, so they are left out of
+/// dictionaries and rejected in lists and variants, the same way the byte
+/// serializer handles them.
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeVariant<SerializeList>;
+    type SerializeMap = SerializeDict;
+    type SerializeStruct = SerializeDict;
+    type SerializeStructVariant = SerializeVariant<SerializeDict>;
+
+    fn serialize_bool(self, v: bool) -> Result<Option<Value>> {
+        self.serialize_i64(if v { 1 } else { 0 })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Option<Value>> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Option<Value>> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Option<Value>> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Option<Value>> {
+        Ok(Some(Value::Integer(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Option<Value>> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Option<Value>> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Option<Value>> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Option<Value>> {
+        Ok(Some(Value::from(v)))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Option<Value>> {
+        Ok(Some(Value::from(v)))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Option<Value>> {
+        Ok(Some(Value::from(v)))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Option<Value>> {
+        Err(Error::Message("bencode does not support floats".into()))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Option<Value>> {
+        Err(Error::Message("bencode does not support floats".into()))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Option<Value>> {
+        self.serialize_str(&v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Option<Value>> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Option<Value>> {
+        Ok(Some(Value::ByteString(v.to_vec())))
+    }
+
+    fn serialize_none(self) -> Result<Option<Value>> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Option<Value>> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Option<Value>> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Option<Value>> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Option<Value>> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Option<Value>> {
+        if name == raw::TOKEN {
+            // The raw bytes arrive as a byte string; parse them into the tree.
+            // They may have been captured leniently, so don't demand canonical form.
+            return match value.serialize(self)? {
+                Some(Value::ByteString(bytes)) => {
+                    let options = DecoderOptions {
+                        big_integers: true,
+                        ..DecoderOptions::lenient()
+                    };
+                    decode_with_options(&bytes, options).map(Some)
+                }
+                _ => Err(Error::Message("invalid raw bencode value".into())),
+            };
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Option<Value>> {
+        let value = value.serialize(self)?.ok_or_else(missing_value)?;
+        Ok(Some(tagged(variant, value)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList> {
+        Ok(SerializeList {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeList> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeDict> {
+        Ok(SerializeDict {
+            map: BTreeMap::new(),
+            current_key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeDict> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+struct SerializeList {
+    items: Vec<Value>,
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let item = value
+            .serialize(ValueSerializer)?
+            .ok_or_else(missing_value)?;
+        self.items.push(item);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Option<Value>> {
+        Ok(Some(Value::List(self.items)))
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Option<Value>> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Option<Value>> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct SerializeDict {
+    map: BTreeMap<Vec<u8>, Value>,
+    current_key: Option<Vec<u8>>,
+}
+
+impl ser::SerializeMap for SerializeDict {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        match key.serialize(ValueSerializer)? {
+            Some(Value::ByteString(key)) => {
+                self.current_key = Some(key);
+                Ok(())
+            }
+            _ => Err(Error::InvalidDictKey),
+        }
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .current_key
+            .take()
+            .ok_or_else(|| Error::Message("serialize_value called before serialize_key".into()))?;
+        if let Some(value) = value.serialize(ValueSerializer)?
+            && self.map.insert(key, value).is_some()
+        {
+            return Err(Error::DuplicateDictKey);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Option<Value>> {
+        Ok(Some(Value::Dict(self.map)))
+    }
+}
+
+impl ser::SerializeStruct for SerializeDict {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        ser::SerializeMap::serialize_key(self, key)?;
+        ser::SerializeMap::serialize_value(self, value)
+    }
+
+    fn end(self) -> Result<Option<Value>> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// Wrap a variant's content in a single-key dict, as externally tagged enums are encoded
+fn tagged(variant: &str, value: Value) -> Value {
+    let mut map = BTreeMap::new();
+    map.insert(variant.as_bytes().to_vec(), value);
+    Value::Dict(map)
+}
+
+/// Collects the content of a tuple or struct variant
+struct SerializeVariant<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeList> {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Option<Value>> {
+        let value = ser::SerializeSeq::end(self.inner)?;
+        Ok(value.map(|value| tagged(self.variant, value)))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeDict> {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Option<Value>> {
+        let value = ser::SerializeMap::end(self.inner)?;
+        Ok(value.map(|value| tagged(self.variant, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+    use crate::{RawBencode, decode, encode, to_bytes};
+
+    #[test]
+    fn value_serializes_to_same_bytes_as_encode() {
+        let value = decode(b"d3:bar4:spam3:fooli1ei-2eee").unwrap();
+        assert_eq!(to_bytes(&value).unwrap(), crate::encode(&value));
+    }
+
+    #[test]
+    fn struct_to_value() {
+        #[derive(Serialize)]
+        struct File {
+            length: u32,
+            path: Vec<String>,
+            md5sum: Option<String>,
+        }
+
+        let file = File {
+            length: 42,
+            path: vec!["a".into(), "b".into()],
+            md5sum: None,
+        };
+        let value = to_value(&file).unwrap();
+        assert_eq!(value, decode(b"d6:lengthi42e4:pathl1:a1:bee").unwrap());
+    }
+
+    #[test]
+    fn raw_to_value() {
+        let raw = RawBencode::from_bytes(b"li1e3:abce").unwrap();
+        assert_eq!(to_value(&raw).unwrap(), decode(b"li1e3:abce").unwrap());
+    }
+
+    #[test]
+    fn non_canonical_raw_to_value() {
+        let raw = RawBencode::from_bytes(b"d1:bi02e1:ai1ee").unwrap();
+        assert_eq!(to_value(&raw).unwrap(), decode(b"d1:ai1e1:bi2ee").unwrap());
+    }
+
+    #[test]
+    fn reject_unrepresentable_values() {
+        assert!(to_value(&1.5f64).is_err());
+        assert!(to_value(&None::<i64>).is_err());
+    }
+
+    #[test]
+    fn wide_integers_to_value() {
+        assert_eq!(
+            to_value(&(i64::MAX as u64)).unwrap(),
+            Value::Integer(i64::MAX)
+        );
+        let value = to_value(&u64::MAX).unwrap();
+        assert_eq!(
+            value.as_big_integer().map(|n| n.as_str()),
+            Some("18446744073709551615")
+        );
+        assert_eq!(to_bytes(&value).unwrap(), b"i18446744073709551615e");
+
+        // Wider than any serde integer: goes through the raw passthrough
+        let huge = Value::BigInteger("1".repeat(50).parse().unwrap());
+        assert_eq!(to_value(&huge).unwrap(), huge);
+        assert_eq!(to_bytes(&huge).unwrap(), encode(&huge));
+    }
+
+    #[test]
+    fn enums_to_value() {
+        #[derive(Serialize)]
+        enum Message {
+            Choke,
+            Have(u32),
+            Request(u32, u32, u32),
+            Extended { id: u8, payload: String },
+        }
+
+        for message in [
+            Message::Choke,
+            Message::Have(7),
+            Message::Request(1, 2, 3),
+            Message::Extended {
+                id: 1,
+                payload: "x".into(),
+            },
+        ] {
+            let value = to_value(&message).unwrap();
+            assert_eq!(to_bytes(&value).unwrap(), to_bytes(&message).unwrap());
+        }
+    }
+}
@@ -0,0 +1,548 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Take};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha1::{Digest, Sha1};
+use trendt_bencode::Error;
+
+use crate::info_hash::InfoHash;
+use crate::torrent::{FileEntry, Info, Torrent};
+
+/// Smallest piece length picked automatically
+const MIN_PIECE_LENGTH: u64 = 16 * 1024;
+
+/// Largest piece length picked automatically
+const MAX_PIECE_LENGTH: u64 = 16 * 1024 * 1024;
+
+/// Largest piece length accepted from [`TorrentBuilder::piece_length`]
+const MAX_FIXED_PIECE_LENGTH: i64 = 64 * 1024 * 1024;
+
+/// Number of pieces an automatic piece length aims for
+const TARGET_PIECE_COUNT: u64 = 1500;
+
+/// Called with the number of pieces hashed so far and the total
+type ProgressFn<'a> = dyn Fn(usize, usize) + Sync + 'a;
+
+/// Creates a new v1 torrent from a file or directory
+///
+/// A directory becomes a multi-file torrent holding every regular file below
+/// it, in path order; symlinks and special files such as sockets are skipped
+/// so the torrent never reaches outside the directory.
+/// Pieces are hashed on several threads at once.
+///
+/// ```no_run
+/// use trendt_torrent::TorrentBuilder;
+///
+/// let torrent = TorrentBuilder::new("dataset/")
+///     .tracker("http://tracker.example/announce")
+///     .comment("Nightly export")
+///     .private(true)
+///     .on_progress(|done, total| eprintln!("{}/{}", done, total))
+///     .build()?;
+/// torrent.to_file("dataset.torrent")?;
+/// # Ok::<(), trendt_bencode::Error>(())
+/// ```
+pub struct TorrentBuilder<'a> {
+    /// The file or directory to share
+    path: PathBuf,
+    /// Bytes per piece, or `None` to pick one from the total size
+    piece_length: Option<i64>,
+    /// One tier per tracker, in order
+    trackers: Vec<String>,
+    /// Free-form comment
+    comment: Option<String>,
+    /// Whether to set the BEP 27 private flag
+    private: bool,
+    /// Number of hashing threads
+    threads: usize,
+    /// Progress callback
+    progress: Option<Box<ProgressFn<'a>>>,
+}
+
+impl<'a> TorrentBuilder<'a> {
+    /// Start a torrent for the file or directory at `path`
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        TorrentBuilder {
+            path: path.as_ref().to_path_buf(),
+            piece_length: None,
+            trackers: Vec::new(),
+            comment: None,
+            private: false,
+            threads: thread::available_parallelism().map_or(1, usize::from),
+            progress: None,
+        }
+    }
+
+    /// Use a fixed piece length instead of picking one from the total size
+    ///
+    /// It must be a power of two from 16 KiB to 64 MiB.
+    pub fn piece_length(mut self, piece_length: i64) -> Self {
+        self.piece_length = Some(piece_length);
+        self
+    }
+
+    /// Add a tracker; the first one becomes `announce`, and with several each
+    /// gets its own tier in `announce-list`
+    pub fn tracker(mut self, url: impl Into<String>) -> Self {
+        self.trackers.push(url.into());
+        self
+    }
+
+    /// Set the comment
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    /// Mark the torrent private (BEP 27)
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+
+    /// Number of threads to hash with; defaults to the available parallelism
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Report progress as pieces are hashed
+    ///
+    /// The callback runs on the hashing threads with the number of pieces
+    /// done so far and the total.
+    pub fn on_progress(mut self, progress: impl Fn(usize, usize) + Sync + 'a) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Read and hash the content
+    pub fn build(self) -> Result<Torrent, Error> {
+        let Some(announce) = self.trackers.first().cloned() else {
+            return Err(Error::Message(
+                "a torrent needs at least one tracker".into(),
+            ));
+        };
+        let name = file_name(&self.path)?;
+
+        let mut sources = Vec::new();
+        let (length, files) = if fs::metadata(&self.path)?.is_dir() {
+            let mut files = Vec::new();
+            collect_files(&self.path, &mut Vec::new(), &mut sources, &mut files)?;
+            if files.is_empty() {
+                return Err(Error::Message(format!(
+                    "{} contains no files",
+                    self.path.display()
+                )));
+            }
+            (None, files)
+        } else {
+            let length = fs::metadata(&self.path)?.len();
+            sources.push(Source {
+                path: self.path.clone(),
+                length,
+            });
+            (Some(to_i64(length)?), Vec::new())
+        };
+
+        let total: u64 = sources.iter().map(|source| source.length).sum();
+        let piece_length = match self.piece_length {
+            Some(piece_length)
+                if !(MIN_PIECE_LENGTH as i64..=MAX_FIXED_PIECE_LENGTH).contains(&piece_length)
+                    || !(piece_length as u64).is_power_of_two() =>
+            {
+                return Err(Error::Message(
+                    "piece length must be a power of two from 16 KiB to 64 MiB".into(),
+                ));
+            }
+            Some(piece_length) => piece_length,
+            None => auto_piece_length(total),
+        };
+        let pieces = hash_pieces(
+            &sources,
+            piece_length as u64,
+            self.threads,
+            self.progress.as_deref(),
+        )?;
+
+        let info = Info {
+            name,
+            piece_length,
+            pieces,
+            length,
+            files,
+            private: self.private.then_some(1),
+            meta_version: None,
+            file_tree: None,
+            extra: BTreeMap::new(),
+        };
+        info.validate()?;
+        let info_hash = InfoHash::of(&trendt_bencode::to_bytes(&info)?);
+
+        Ok(Torrent {
+            announce,
+            announce_list: (self.trackers.len() > 1)
+                .then(|| self.trackers.iter().map(|url| vec![url.clone()]).collect()),
+            creation_date: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|elapsed| elapsed.as_secs() as i64),
+            comment: self.comment,
+            created_by: Some(concat!("trendt/", env!("CARGO_PKG_VERSION")).into()),
+            info,
+            piece_layers: BTreeMap::new(),
+            extra: BTreeMap::new(),
+            info_hash,
+            info_hash_v2: None,
+        })
+    }
+}
+
+/// Pick a power of two giving roughly [`TARGET_PIECE_COUNT`] pieces
+fn auto_piece_length(total: u64) -> i64 {
+    (total / TARGET_PIECE_COUNT)
+        .next_power_of_two()
+        .clamp(MIN_PIECE_LENGTH, MAX_PIECE_LENGTH) as i64
+}
+
+/// A file on disk contributing to the torrent's content
+struct Source {
+    path: PathBuf,
+    length: u64,
+}
+
+/// Add every regular file below `dir`, sorted by path, to `sources` and
+/// `files`, without following symlinks
+fn collect_files(
+    dir: &Path,
+    prefix: &mut Vec<String>,
+    sources: &mut Vec<Source>,
+    files: &mut Vec<FileEntry>,
+) -> Result<(), Error> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let file_type = entry.file_type()?;
+        if !file_type.is_dir() && !file_type.is_file() {
+            continue;
+        }
+        let path = entry.path();
+        let name = entry
+            .file_name()
+            .into_string()
+            .map_err(|name| Error::Message(format!("file name {:?} is not UTF-8", name)))?;
+        prefix.push(name);
+        if file_type.is_dir() {
+            collect_files(&path, prefix, sources, files)?;
+        } else {
+            let metadata = entry.metadata()?;
+            files.push(FileEntry {
+                length: to_i64(metadata.len())?,
+                path: prefix.clone(),
+                md5sum: None,
+                attr: None,
+                extra: BTreeMap::new(),
+            });
+            sources.push(Source {
+                path,
+                length: metadata.len(),
+            });
+        }
+        prefix.pop();
+    }
+    Ok(())
+}
+
+fn file_name(path: &Path) -> Result<String, Error> {
+    let name = path
+        .canonicalize()?
+        .file_name()
+        .ok_or_else(|| Error::Message(format!("{} has no file name", path.display())))?
+        .to_owned();
+    name.into_string()
+        .map_err(|name| Error::Message(format!("file name {:?} is not UTF-8", name)))
+}
+
+fn to_i64(length: u64) -> Result<i64, Error> {
+    i64::try_from(length)
+        .map_err(|_| Error::Message(format!("file of {} bytes is too large", length)))
+}
+
+/// Hash every piece, splitting them into one contiguous run per thread
+fn hash_pieces(
+    sources: &[Source],
+    piece_length: u64,
+    threads: usize,
+    progress: Option<&ProgressFn<'_>>,
+) -> Result<Vec<u8>, Error> {
+    let total: u64 = sources.iter().map(|source| source.length).sum();
+    let piece_count = total.div_ceil(piece_length) as usize;
+    let mut pieces = vec![0; piece_count * 20];
+    if piece_count == 0 {
+        return Ok(pieces);
+    }
+
+    let per_thread = piece_count.div_ceil(threads);
+    let done = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        let workers: Vec<_> = pieces
+            .chunks_mut(per_thread * 20)
+            .enumerate()
+            .map(|(run, hashes)| {
+                let done = &done;
+                scope.spawn(move || -> io::Result<()> {
+                    let first = (run * per_thread) as u64;
+                    let mut reader = ContentReader::new(sources, first * piece_length);
+                    let mut buffer = vec![0; piece_length as usize];
+                    for (index, hash) in (first..).zip(hashes.chunks_exact_mut(20)) {
+                        let size = piece_length.min(total - index * piece_length) as usize;
+                        reader.read_exact(&mut buffer[..size])?;
+                        hash.copy_from_slice(&Sha1::digest(&buffer[..size]));
+                        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                        if let Some(progress) = progress {
+                            progress(done, piece_count);
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().expect("hashing thread panicked"))
+    })?;
+    Ok(pieces)
+}
+
+/// Reads the concatenated content of several files from some offset on
+struct ContentReader<'a> {
+    sources: &'a [Source],
+    /// The file being read
+    index: usize,
+    /// Where reading starts in that file
+    offset: u64,
+    /// The open file, limited to its length when scanned
+    file: Option<Take<File>>,
+}
+
+impl<'a> ContentReader<'a> {
+    fn new(sources: &'a [Source], mut offset: u64) -> Self {
+        let mut index = 0;
+        while index < sources.len() && offset >= sources[index].length {
+            offset -= sources[index].length;
+            index += 1;
+        }
+        ContentReader {
+            sources,
+            index,
+            offset,
+            file: None,
+        }
+    }
+
+    fn read_exact(&mut self, mut buffer: &mut [u8]) -> io::Result<()> {
+        while !buffer.is_empty() {
+            let source = self
+                .sources
+                .get(self.index)
+                .ok_or(io::ErrorKind::UnexpectedEof)?;
+            let file = match &mut self.file {
+                Some(file) => file,
+                None => {
+                    let mut file = File::open(&source.path)?;
+                    file.seek(SeekFrom::Start(self.offset))?;
+                    self.file.insert(file.take(source.length - self.offset))
+                }
+            };
+            match file.read(buffer)? {
+                // The file shrank since it was scanned
+                0 if file.limit() > 0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                0 => {
+                    self.index += 1;
+                    self.offset = 0;
+                    self.file = None;
+                }
+                n => buffer = &mut buffer[n..],
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::test_data::content;
+
+    /// A fresh directory under the system temp dir
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("trendt-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn expected_pieces(content: &[u8], piece_length: usize) -> Vec<u8> {
+        content
+            .chunks(piece_length)
+            .flat_map(|piece| Sha1::digest(piece).to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn single_file() {
+        let dir = temp_dir("single");
+        let data = content(100_000, 1);
+        fs::write(dir.join("data.bin"), &data).unwrap();
+
+        let calls = Mutex::new(Vec::new());
+        let torrent = TorrentBuilder::new(dir.join("data.bin"))
+            .piece_length(16384)
+            .tracker("http://a.example/announce")
+            .comment("test")
+            .threads(3)
+            .on_progress(|done, total| calls.lock().unwrap().push((done, total)))
+            .build()
+            .unwrap();
+
+        assert_eq!(torrent.announce, "http://a.example/announce");
+        assert_eq!(torrent.announce_list, None);
+        assert_eq!(torrent.comment.as_deref(), Some("test"));
+        assert_eq!(torrent.info.name, "data.bin");
+        assert_eq!(torrent.info.length, Some(100_000));
+        assert!(!torrent.info.is_private());
+        assert_eq!(torrent.info.pieces, expected_pieces(&data, 16384));
+
+        let mut calls = calls.into_inner().unwrap();
+        calls.sort();
+        assert_eq!(calls, (1..=7).map(|done| (done, 7)).collect::<Vec<_>>());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn directory() {
+        let dir = temp_dir("directory").join("set");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let (a, b, c) = (content(5000, 2), content(40_000, 3), content(1, 4));
+        fs::write(dir.join("b.bin"), &b).unwrap();
+        fs::write(dir.join("a.bin"), &a).unwrap();
+        fs::write(dir.join("empty"), b"").unwrap();
+        fs::write(dir.join("sub").join("c.bin"), &c).unwrap();
+
+        let torrent = TorrentBuilder::new(&dir)
+            .piece_length(16384)
+            .tracker("http://a.example/announce")
+            .tracker("udp://b.example:6969")
+            .private(true)
+            .threads(2)
+            .build()
+            .unwrap();
+
+        let info = &torrent.info;
+        assert_eq!(info.name, "set");
+        assert!(info.is_private());
+        assert_eq!(
+            info.files
+                .iter()
+                .map(|file| (file.path.join("/"), file.length))
+                .collect::<Vec<_>>(),
+            [
+                ("a.bin".into(), 5000),
+                ("b.bin".into(), 40_000),
+                ("empty".into(), 0),
+                ("sub/c.bin".into(), 1)
+            ]
+        );
+        assert_eq!(info.pieces, expected_pieces(&[a, b, c].concat(), 16384));
+        assert_eq!(
+            torrent.announce_list,
+            Some(vec![
+                vec!["http://a.example/announce".into()],
+                vec!["udp://b.example:6969".into()]
+            ])
+        );
+
+        // The built torrent reloads with the same info-hash
+        let reloaded = Torrent::from_bytes(&torrent.to_bytes().unwrap()).unwrap();
+        assert_eq!(reloaded.info_hash(), torrent.info_hash());
+        assert_eq!(reloaded.info.pieces, torrent.info.pieces);
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skip_symlinks_and_special_files() {
+        use std::os::unix::{fs::symlink, net::UnixListener};
+
+        let dir = temp_dir("special").join("set");
+        fs::create_dir_all(&dir).unwrap();
+        let data = content(3000, 5);
+        fs::write(dir.join("a.bin"), &data).unwrap();
+        symlink(dir.join("a.bin"), dir.join("link.bin")).unwrap();
+        symlink(&dir, dir.join("loop")).unwrap();
+        symlink(std::env::temp_dir(), dir.join("outside")).unwrap();
+        let _socket = UnixListener::bind(dir.join("socket")).unwrap();
+
+        let torrent = TorrentBuilder::new(&dir)
+            .piece_length(16384)
+            .tracker("http://a.example/announce")
+            .build()
+            .unwrap();
+        assert_eq!(
+            torrent
+                .info
+                .files
+                .iter()
+                .map(|file| file.path.join("/"))
+                .collect::<Vec<_>>(),
+            ["a.bin"]
+        );
+        assert_eq!(torrent.info.pieces, expected_pieces(&data, 16384));
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn errors() {
+        let dir = temp_dir("errors");
+        assert!(
+            TorrentBuilder::new(&dir)
+                .build()
+                .unwrap_err()
+                .to_string()
+                .contains("tracker")
+        );
+        let err = TorrentBuilder::new(&dir)
+            .tracker("http://a.example/announce")
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("contains no files"), "{}", err);
+        fs::write(dir.join("a.bin"), b"a").unwrap();
+        for piece_length in [0, -16384, 1000, 8192, 3 * 16384, 1 << 40] {
+            let err = TorrentBuilder::new(&dir)
+                .tracker("http://a.example/announce")
+                .piece_length(piece_length)
+                .build()
+                .unwrap_err();
+            assert!(err.to_string().contains("power of two"), "{}", err);
+        }
+        assert!(
+            TorrentBuilder::new(dir.join("missing"))
+                .tracker("http://a.example/announce")
+                .build()
+                .is_err()
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn automatic_piece_length() {
+        assert_eq!(auto_piece_length(0), 16 * 1024);
+        assert_eq!(auto_piece_length(1 << 30), 1 << 20);
+        assert_eq!(auto_piece_length(1 << 50), 16 * 1024 * 1024);
+    }
+}
@@ -0,0 +1,204 @@
+use trendt_bencode::Error;
+
+use crate::info_hash::InfoHash;
+use crate::torrent::{FileEntry, Info, Layout, Torrent, invalid};
+
+/// Which BitTorrent versions a torrent's metadata supports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    /// SHA-1 pieces only
+    V1,
+    /// A BEP 52 file tree only
+    V2,
+    /// Both, describing the same content
+    Hybrid,
+}
+
+impl FileEntry {
+    /// Whether this is a BEP 47 padding file, inserted only to align the next file
+    pub fn is_padding(&self) -> bool {
+        self.attr.as_deref().is_some_and(|attr| attr.contains('p'))
+    }
+}
+
+impl Info {
+    /// Whether the torrent has both a v1 layout and a v2 file tree
+    pub fn is_hybrid(&self) -> bool {
+        self.is_v1() && self.is_v2()
+    }
+
+    /// Which versions the metadata supports
+    pub fn version(&self) -> Version {
+        match (self.is_v1(), self.is_v2()) {
+            (true, true) => Version::Hybrid,
+            (false, true) => Version::V2,
+            _ => Version::V1,
+        }
+    }
+
+    /// Check that the v1 files, less padding, match the v2 file tree one for one
+    ///
+    /// In a hybrid torrent every v1 file must also start on a piece boundary,
+    /// so that v1 and v2 pieces cover the same bytes.
+    pub(crate) fn validate_hybrid(&self) -> Result<(), Error> {
+        let v1 = match self.layout() {
+            Layout::SingleFile { length } => vec![(vec![self.name.clone()], length)],
+            Layout::MultiFile { files } => {
+                let mut offset = 0;
+                let mut v1 = Vec::new();
+                for file in files {
+                    if !file.is_padding() {
+                        if file.length > 0 && offset % self.piece_length != 0 {
+                            return invalid(&format!(
+                                "{} does not start on a piece boundary",
+                                file.path.join("/")
+                            ));
+                        }
+                        v1.push((file.path.clone(), file.length));
+                    }
+                    offset += file.length;
+                }
+                v1
+            }
+        };
+        let v2: Vec<_> = self
+            .v2_files()
+            .into_iter()
+            .map(|(path, file)| (path, file.length))
+            .collect();
+
+        for ((v1_path, v1_length), (v2_path, v2_length)) in v1.iter().zip(&v2) {
+            if (v1_path, v1_length) != (v2_path, v2_length) {
+                return invalid(&format!(
+                    "v1 file {} ({} bytes) does not match v2 file {} ({} bytes)",
+                    v1_path.join("/"),
+                    v1_length,
+                    v2_path.join("/"),
+                    v2_length
+                ));
+            }
+        }
+        if v1.len() != v2.len() {
+            return invalid(&format!(
+                "v1 has {} files but the v2 file tree has {}",
+                v1.len(),
+                v2.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Torrent {
+    /// The 20-byte hashes to announce to trackers and accept in handshakes
+    ///
+    /// The v1 info-hash for v1 swarms and the truncated v2 info-hash for v2
+    /// swarms; a hybrid torrent joins both.
+    pub fn swarm_hashes(&self) -> Vec<InfoHash> {
+        let mut hashes = Vec::new();
+        if self.info.is_v1() {
+            hashes.push(self.info_hash());
+        }
+        if let Some(hash) = self.info_hash_v2() {
+            hashes.push(hash.truncated());
+        }
+        hashes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use trendt_bencode::{Value, bencode, encode};
+
+    use super::*;
+    use crate::test_data;
+    use crate::v2::{BLOCK_SIZE, file_root, piece_layer};
+
+    const PIECE_LENGTH: usize = BLOCK_SIZE;
+
+    /// A two-file hybrid torrent; `pad` is the length of the padding after `a.txt`
+    fn hybrid(pad: i64, b_name: &str) -> Value {
+        let a = vec![1; 100];
+        let b = vec![2; PIECE_LENGTH + 5];
+        let mut files = vec![bencode! { "length" => 100, "path" => ["a.txt"] }];
+        if pad > 0 {
+            files.push(
+                bencode! { "attr" => "p", "length" => pad, "path" => [".pad", pad.to_string()] },
+            );
+        }
+        files.push(bencode! { "length" => b.len() as i64, "path" => [b_name] });
+        let total = 100 + pad + b.len() as i64;
+        let pieces = (total as usize).div_ceil(PIECE_LENGTH);
+
+        bencode! {
+            "announce" => "http://tracker.example/announce",
+            "info" => {
+                "file tree" => {
+                    "a.txt" => { "" => { "length" => 100, "pieces root" => &file_root(&a) } },
+                    "b.bin" => { "" => { "length" => b.len() as i64, "pieces root" => &file_root(&b) } },
+                },
+                "files" => Value::List(files),
+                "meta version" => 2,
+                "name" => "set",
+                "piece length" => PIECE_LENGTH as i64,
+                "pieces" => vec![0u8; pieces * 20],
+            },
+            "piece layers" => { file_root(&b) => piece_layer(&b, PIECE_LENGTH).concat() },
+        }
+    }
+
+    #[test]
+    fn hybrid_torrent() {
+        let torrent =
+            Torrent::from_bytes(&encode(&hybrid(PIECE_LENGTH as i64 - 100, "b.bin"))).unwrap();
+        let info = &torrent.info;
+        assert!(info.is_hybrid());
+        assert_eq!(info.version(), Version::Hybrid);
+        assert!(info.files[1].is_padding());
+        assert!(!info.files[0].is_padding());
+
+        let v2 = torrent.info_hash_v2().unwrap();
+        assert_eq!(
+            torrent.swarm_hashes(),
+            [torrent.info_hash(), v2.truncated()]
+        );
+        let magnet = torrent.to_magnet();
+        assert_eq!(magnet.info_hash, Some(torrent.info_hash()));
+        assert_eq!(magnet.info_hash_v2, Some(v2));
+    }
+
+    #[test]
+    fn versions() {
+        let v1 = Torrent::from_bytes(&test_data::torrent(b"8:announce3:url", b"")).unwrap();
+        assert_eq!(v1.info.version(), Version::V1);
+        assert_eq!(v1.swarm_hashes(), [v1.info_hash()]);
+
+        let mut value = hybrid(PIECE_LENGTH as i64 - 100, "b.bin");
+        let info = value["info"].as_dict_mut().unwrap();
+        info.remove(&b"files"[..]);
+        info.remove(&b"pieces"[..]);
+        let v2 = Torrent::from_bytes(&encode(&value)).unwrap();
+        assert_eq!(v2.info.version(), Version::V2);
+        assert_eq!(v2.swarm_hashes(), [v2.info_hash_v2().unwrap().truncated()]);
+    }
+
+    #[test]
+    fn inconsistent_hybrids_are_rejected() {
+        let err = |value: Value| {
+            Torrent::from_bytes(&encode(&value))
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert!(err(hybrid(0, "b.bin")).contains("piece boundary"));
+        assert!(
+            err(hybrid(PIECE_LENGTH as i64 - 100, "c.bin"))
+                .contains("does not match v2 file b.bin")
+        );
+
+        let mut value = hybrid(PIECE_LENGTH as i64 - 100, "b.bin");
+        value["info"]["files"].as_list_mut().unwrap().truncate(2);
+        value["info"]["pieces"] = Value::from(vec![0u8; 20]);
+        assert!(err(value).contains("v1 has 1 files but the v2 file tree has 2"));
+    }
+}
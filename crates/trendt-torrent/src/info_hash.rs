@@ -0,0 +1,286 @@
+use std::fmt;
+use std::str::FromStr;
+
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use trendt_bencode::Error;
+
+/// RFC 4648 base32 alphabet, as used by magnet links
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// The SHA-1 of a torrent's bencoded info dictionary (BitTorrent v1)
+///
+/// Identifies the torrent to trackers and peers. Displays as lowercase hex;
+/// parses from hex or from the base32 form found in older magnet links.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InfoHash(pub [u8; 20]);
+
+impl InfoHash {
+    /// Hash the encoded bytes of an info dictionary
+    pub fn of(info: &[u8]) -> Self {
+        InfoHash(Sha1::digest(info).into())
+    }
+
+    /// The raw 20 bytes
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+
+    /// 40 lowercase hex digits
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.0)
+    }
+
+    /// 32 uppercase base32 characters
+    pub fn to_base32(&self) -> String {
+        to_base32(&self.0)
+    }
+
+    /// Percent-encoded for the `info_hash` parameter of a tracker announce
+    pub fn to_url_encoded(&self) -> String {
+        to_url_encoded(&self.0)
+    }
+
+    /// Parse 40 hex digits, in either case
+    pub fn from_hex(s: &str) -> Result<Self, Error> {
+        from_hex(s).map(InfoHash)
+    }
+
+    /// Parse 32 base32 characters, in either case
+    pub fn from_base32(s: &str) -> Result<Self, Error> {
+        from_base32(s).map(InfoHash)
+    }
+
+    /// Parse the percent-encoded form sent to trackers
+    pub fn from_url_encoded(s: &str) -> Result<Self, Error> {
+        from_url_encoded(s).map(InfoHash)
+    }
+}
+
+impl fmt::Display for InfoHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for InfoHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "InfoHash({})", self)
+    }
+}
+
+/// Accepts hex or base32, told apart by length
+impl FromStr for InfoHash {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s.len() {
+            32 => Self::from_base32(s),
+            _ => Self::from_hex(s),
+        }
+    }
+}
+
+/// The SHA-256 of a torrent's bencoded info dictionary (BitTorrent v2)
+///
+/// Displays and parses as 64 lowercase hex digits.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InfoHashV2(pub [u8; 32]);
+
+impl InfoHashV2 {
+    /// Hash the encoded bytes of an info dictionary
+    pub fn of(info: &[u8]) -> Self {
+        InfoHashV2(Sha256::digest(info).into())
+    }
+
+    /// The first 20 bytes, used where protocols expect a v1-sized hash
+    pub fn truncated(&self) -> InfoHash {
+        InfoHash(self.0[..20].try_into().unwrap())
+    }
+
+    /// The raw 32 bytes
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// 64 lowercase hex digits
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.0)
+    }
+
+    /// Parse 64 hex digits, in either case
+    pub fn from_hex(s: &str) -> Result<Self, Error> {
+        from_hex(s).map(InfoHashV2)
+    }
+}
+
+impl fmt::Display for InfoHashV2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for InfoHashV2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "InfoHashV2({})", self)
+    }
+}
+
+impl FromStr for InfoHashV2 {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Self::from_hex(s)
+    }
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub(crate) fn from_hex<const N: usize>(s: &str) -> Result<[u8; N], Error> {
+    let invalid = || invalid(s, "hex");
+    if s.len() != N * 2 || !s.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let mut bytes = [0; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(bytes)
+}
+
+/// Base32 without padding
+fn to_base32(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | u16::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(char::from(BASE32[usize::from((buffer >> bits) & 31)]));
+        }
+    }
+    if bits > 0 {
+        out.push(char::from(BASE32[usize::from((buffer << (5 - bits)) & 31)]));
+    }
+    out
+}
+
+fn from_base32<const N: usize>(s: &str) -> Result<[u8; N], Error> {
+    if s.len() != (N * 8).div_ceil(5) {
+        return Err(invalid(s, "base32"));
+    }
+    let mut bytes = [0; N];
+    let (mut buffer, mut bits, mut filled) = (0u16, 0, 0);
+    for c in s.bytes() {
+        let digit = BASE32
+            .iter()
+            .position(|&d| d == c.to_ascii_uppercase())
+            .ok_or_else(|| invalid(s, "base32"))?;
+        buffer = (buffer << 5) | digit as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes[filled] = (buffer >> bits) as u8;
+            filled += 1;
+        }
+    }
+    // Leftover bits are padding and must be zero
+    if buffer & ((1 << bits) - 1) != 0 {
+        return Err(invalid(s, "base32"));
+    }
+    Ok(bytes)
+}
+
+/// Percent-encode everything except RFC 3986 unreserved characters
+pub(crate) fn to_url_encoded(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn from_url_encoded<const N: usize>(s: &str) -> Result<[u8; N], Error> {
+    percent_decode(s, false)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| invalid(s, "percent-encoded"))
+}
+
+/// Undo percent-encoding, optionally reading `+` as a space as in query strings
+pub(crate) fn percent_decode(s: &str, plus_as_space: bool) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'%' => {
+                let digits = std::str::from_utf8(tail.get(..2)?).ok()?;
+                if !digits.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+                    return None;
+                }
+                bytes.push(u8::from_str_radix(digits, 16).ok()?);
+                rest = &tail[2..];
+            }
+            b'+' if plus_as_space => bytes.push(b' '),
+            _ => bytes.push(byte),
+        }
+    }
+    Some(bytes)
+}
+
+fn invalid(s: &str, encoding: &str) -> Error {
+    Error::Message(format!("invalid {} info-hash {:?}", encoding, s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEX: &str = "d8b4d1c2b5c5e3e1f0a2b3c4d5e6f708192a3b4c";
+
+    #[test]
+    fn hex() {
+        let hash: InfoHash = HEX.parse().unwrap();
+        assert_eq!(hash.to_string(), HEX);
+        assert_eq!(InfoHash::from_hex(&HEX.to_uppercase()).unwrap(), hash);
+        assert_eq!(format!("{:?}", hash), format!("InfoHash({})", HEX));
+        assert!(InfoHash::from_hex(&HEX[..38]).is_err());
+        assert!(InfoHash::from_hex(&HEX.replace('d', "g")).is_err());
+    }
+
+    #[test]
+    fn base32() {
+        let hash = InfoHash::from_hex(HEX).unwrap();
+        let base32 = hash.to_base32();
+        assert_eq!(base32, "3C2NDQVVYXR6D4FCWPCNLZXXBAMSUO2M");
+        assert_eq!(base32.parse::<InfoHash>().unwrap(), hash);
+        assert_eq!(InfoHash::from_base32(&base32.to_lowercase()).unwrap(), hash);
+        assert!(InfoHash::from_base32("3C2NDQVVYXR6D4FCWPCNLZXXBAMSUO21").is_err());
+
+        assert_eq!(InfoHash([0; 20]).to_base32(), "A".repeat(32));
+        assert_eq!(InfoHash([0xff; 20]).to_base32(), "7".repeat(32));
+    }
+
+    #[test]
+    fn url_encoded() {
+        let mut bytes = [b'a'; 20];
+        bytes[..4].copy_from_slice(&[0x00, b' ', b'~', 0xff]);
+        let hash = InfoHash(bytes);
+        let encoded = hash.to_url_encoded();
+        assert_eq!(encoded, format!("%00%20~%FF{}", "a".repeat(16)));
+        assert_eq!(InfoHash::from_url_encoded(&encoded).unwrap(), hash);
+        assert_eq!(
+            InfoHash::from_url_encoded(&encoded.replace("%FF", "%ff")).unwrap(),
+            hash
+        );
+        assert!(InfoHash::from_url_encoded(&encoded[..encoded.len() - 1]).is_err());
+        assert!(InfoHash::from_url_encoded("%0").is_err());
+    }
+}
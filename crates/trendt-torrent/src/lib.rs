@@ -1,3 +1,18 @@
+pub mod builder;
+pub mod hybrid;
+pub mod info_hash;
+pub mod magnet;
+pub mod pieces;
 pub mod torrent;
+pub mod v2;
 
-pub use torrent::Torrent;
+#[cfg(test)]
+mod test_data;
+
+pub use builder::TorrentBuilder;
+pub use hybrid::Version;
+pub use info_hash::{InfoHash, InfoHashV2};
+pub use magnet::Magnet;
+pub use pieces::PieceHash;
+pub use torrent::{FileEntry, FileSpan, Info, Layout, Torrent};
+pub use v2::{FileTreeNode, TreeFile};
@@ -0,0 +1,292 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use trendt_bencode::{Error, Value};
+
+use crate::info_hash::{InfoHash, InfoHashV2, percent_decode, to_url_encoded};
+use crate::torrent::Torrent;
+
+/// Multihash prefix of a SHA-256 digest: function code 0x12, length 0x20
+const SHA256_MULTIHASH: &str = "1220";
+
+/// A `magnet:` link identifying a torrent by its info-hash (BEP 9)
+///
+/// Parses v1 (`urn:btih:`, hex or base32) and v2 (`urn:btmh:`) exact topics
+/// along with the parameters below; unknown parameters are ignored. Displays
+/// in the same form, with hex hashes and percent-encoded values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Magnet {
+    /// `xt=urn:btih:`, the v1 info-hash
+    pub info_hash: Option<InfoHash>,
+    /// `xt=urn:btmh:`, the v2 info-hash
+    pub info_hash_v2: Option<InfoHashV2>,
+    /// `dn`, a name to show until the metadata arrives
+    pub display_name: Option<String>,
+    /// `xl`, the total size in bytes
+    pub exact_length: Option<i64>,
+    /// `tr`, tracker URLs
+    pub trackers: Vec<String>,
+    /// `ws`, web seed URLs (BEP 19)
+    pub web_seeds: Vec<String>,
+    /// `x.pe`, peer addresses as `host:port`
+    pub peers: Vec<String>,
+    /// `so`, indices of the files to download (BEP 53)
+    pub select_only: Vec<RangeInclusive<usize>>,
+}
+
+impl Magnet {
+    /// Take one `xt` value; topics other than BitTorrent ones are skipped
+    fn parse_topic(&mut self, topic: &str) -> Result<(), Error> {
+        if let Some(hash) = topic.strip_prefix("urn:btih:") {
+            self.info_hash = Some(hash.parse()?);
+        } else if let Some(multihash) = topic.strip_prefix("urn:btmh:") {
+            let hash = multihash
+                .get(..SHA256_MULTIHASH.len())
+                .filter(|prefix| *prefix == SHA256_MULTIHASH)
+                .and_then(|_| multihash.get(SHA256_MULTIHASH.len()..))
+                .ok_or_else(|| invalid(&format!("unsupported multihash {:?}", multihash)))?;
+            self.info_hash_v2 = Some(hash.parse()?);
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Magnet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let query = s
+            .strip_prefix("magnet:?")
+            .ok_or_else(|| invalid("missing magnet:? prefix"))?;
+        let mut magnet = Magnet::default();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value, true)
+                .and_then(|value| String::from_utf8(value).ok())
+                .ok_or_else(|| invalid(&format!("bad encoding in {:?}", pair)))?;
+            // Numbered keys such as `tr.1` repeat the plain key
+            let key = match key.rsplit_once('.') {
+                Some((base, n)) if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => base,
+                _ => key,
+            };
+            match key {
+                "xt" => magnet.parse_topic(&value)?,
+                "dn" => magnet.display_name = Some(value),
+                "xl" => {
+                    let length = value
+                        .parse()
+                        .ok()
+                        .filter(|length| *length >= 0)
+                        .ok_or_else(|| invalid(&format!("bad length {:?}", value)))?;
+                    magnet.exact_length = Some(length);
+                }
+                "tr" => magnet.trackers.push(value),
+                "ws" => magnet.web_seeds.push(value),
+                "x.pe" => magnet.peers.push(value),
+                "so" => magnet.select_only.extend(parse_ranges(&value)?),
+                _ => {}
+            }
+        }
+        if magnet.info_hash.is_none() && magnet.info_hash_v2.is_none() {
+            return Err(invalid("no BitTorrent info-hash"));
+        }
+        Ok(magnet)
+    }
+}
+
+impl fmt::Display for Magnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut params = Vec::new();
+        if let Some(hash) = &self.info_hash {
+            params.push(format!("xt=urn:btih:{}", hash));
+        }
+        if let Some(hash) = &self.info_hash_v2 {
+            params.push(format!("xt=urn:btmh:{}{}", SHA256_MULTIHASH, hash));
+        }
+        let encode = |value: &str| to_url_encoded(value.as_bytes());
+        if let Some(name) = &self.display_name {
+            params.push(format!("dn={}", encode(name)));
+        }
+        if let Some(length) = self.exact_length {
+            params.push(format!("xl={}", length));
+        }
+        params.extend(
+            self.trackers
+                .iter()
+                .map(|url| format!("tr={}", encode(url))),
+        );
+        params.extend(
+            self.web_seeds
+                .iter()
+                .map(|url| format!("ws={}", encode(url))),
+        );
+        params.extend(
+            self.peers
+                .iter()
+                .map(|peer| format!("x.pe={}", encode(peer))),
+        );
+        if !self.select_only.is_empty() {
+            let ranges: Vec<_> = self
+                .select_only
+                .iter()
+                .map(|range| match (range.start(), range.end()) {
+                    (start, end) if start == end => start.to_string(),
+                    (start, end) => format!("{}-{}", start, end),
+                })
+                .collect();
+            params.push(format!("so={}", ranges.join(",")));
+        }
+        write!(f, "magnet:?{}", params.join("&"))
+    }
+}
+
+/// Parse a BEP 53 list such as `0,2,4-6`
+fn parse_ranges(list: &str) -> Result<Vec<RangeInclusive<usize>>, Error> {
+    list.split(',')
+        .map(|item| {
+            let (start, end) = item.split_once('-').unwrap_or((item, item));
+            match (start.parse(), end.parse()) {
+                (Ok(start), Ok(end)) if start <= end => Ok(start..=end),
+                _ => Err(invalid(&format!("bad file range {:?}", item))),
+            }
+        })
+        .collect()
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Message(format!("invalid magnet link: {}", message))
+}
+
+impl Torrent {
+    /// A magnet link with the info-hashes, name, size, trackers and web seeds
+    pub fn to_magnet(&self) -> Magnet {
+        let mut trackers = vec![self.announce.clone()];
+        for url in self.announce_list.iter().flatten().flatten() {
+            if !trackers.contains(url) {
+                trackers.push(url.clone());
+            }
+        }
+        // `url-list` (BEP 19) is either one URL or a list of them
+        let web_seeds = match self.extra.get("url-list") {
+            Some(Value::List(urls)) => urls
+                .iter()
+                .filter_map(Value::as_str)
+                .map(String::from)
+                .collect(),
+            Some(url) => url.as_str().map(String::from).into_iter().collect(),
+            None => Vec::new(),
+        };
+        Magnet {
+            info_hash: self.info.is_v1().then(|| self.info_hash()),
+            info_hash_v2: self.info_hash_v2(),
+            display_name: Some(self.info.name.clone()),
+            exact_length: Some(self.info.total_length()),
+            trackers,
+            web_seeds,
+            peers: Vec::new(),
+            select_only: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_data;
+
+    const HEX: &str = test_data::INFO_HASH;
+    const V2: &str = "caf1e1c30e81cb361b9ee167c4aa64228a7fa4fa9f6105232b28ad099f3a302e";
+
+    #[test]
+    fn parse_v1() {
+        let link = format!(
+            "magnet:?xt=urn:btih:{}&dn=Debian+12%20netinst&xl=658505728&tr=http%3A%2F%2Ftracker.example%2Fannounce&tr.1=udp://b.example:6969&ws=http://seed.example/&x.pe=10.0.0.1:6881&so=0,2,4-6&foo=bar",
+            HEX.to_uppercase()
+        );
+        let magnet: Magnet = link.parse().unwrap();
+        assert_eq!(magnet.info_hash.unwrap().to_hex(), HEX);
+        assert_eq!(magnet.info_hash_v2, None);
+        assert_eq!(magnet.display_name.as_deref(), Some("Debian 12 netinst"));
+        assert_eq!(magnet.exact_length, Some(658505728));
+        assert_eq!(
+            magnet.trackers,
+            ["http://tracker.example/announce", "udp://b.example:6969"]
+        );
+        assert_eq!(magnet.web_seeds, ["http://seed.example/"]);
+        assert_eq!(magnet.peers, ["10.0.0.1:6881"]);
+        assert_eq!(magnet.select_only, [0..=0, 2..=2, 4..=6]);
+
+        let formatted = magnet.to_string();
+        assert_eq!(
+            formatted,
+            format!(
+                "magnet:?xt=urn:btih:{}&dn=Debian%2012%20netinst&xl=658505728&tr=http%3A%2F%2Ftracker.example%2Fannounce&tr=udp%3A%2F%2Fb.example%3A6969&ws=http%3A%2F%2Fseed.example%2F&x.pe=10.0.0.1%3A6881&so=0,2,4-6",
+                HEX
+            )
+        );
+        assert_eq!(formatted.parse::<Magnet>().unwrap(), magnet);
+    }
+
+    #[test]
+    fn numbered_keys() {
+        let link = format!(
+            "magnet:?xt=urn:btih:{}&x.pe.1=10.0.0.1:6881&x.pe.2=10.0.0.2:6881&tr.x=ignored",
+            HEX
+        );
+        let magnet: Magnet = link.parse().unwrap();
+        assert_eq!(magnet.peers, ["10.0.0.1:6881", "10.0.0.2:6881"]);
+        assert!(magnet.trackers.is_empty());
+    }
+
+    #[test]
+    fn base32_and_v2() {
+        let hash = InfoHash::from_hex(HEX).unwrap();
+        let link = format!(
+            "magnet:?xt=urn:btih:{}&xt=urn:btmh:1220{}",
+            hash.to_base32(),
+            V2
+        );
+        let magnet: Magnet = link.parse().unwrap();
+        assert_eq!(magnet.info_hash, Some(hash));
+        assert_eq!(magnet.info_hash_v2.unwrap().to_hex(), V2);
+        assert_eq!(magnet.to_string().parse::<Magnet>().unwrap(), magnet);
+
+        let v2_only: Magnet = format!("magnet:?xt=urn:btmh:1220{}", V2).parse().unwrap();
+        assert_eq!(v2_only.info_hash, None);
+        assert_eq!(
+            v2_only.to_string(),
+            format!("magnet:?xt=urn:btmh:1220{}", V2)
+        );
+    }
+
+    #[test]
+    fn invalid_links() {
+        for link in [
+            "http://example/",
+            "magnet:?dn=x",
+            "magnet:?xt=urn:btih:1234",
+            "magnet:?xt=urn:btmh:1114abcd",
+            &format!("magnet:?xt=urn:btih:{}&so=3-1", HEX),
+            &format!("magnet:?xt=urn:btih:{}&xl=-1", HEX),
+            &format!("magnet:?xt=urn:btih:{}&dn=%ff", HEX),
+        ] {
+            assert!(link.parse::<Magnet>().is_err(), "{}", link);
+        }
+    }
+
+    #[test]
+    fn from_torrent() {
+        let torrent = Torrent::from_bytes(&test_data::torrent(
+            b"8:announce3:url13:announce-listll3:urlel5:otheree",
+            b"8:url-list4:seed",
+        ))
+        .unwrap();
+        let magnet = torrent.to_magnet();
+        assert_eq!(magnet.info_hash.unwrap().to_hex(), HEX);
+        assert_eq!(magnet.display_name.as_deref(), Some("a.txt"));
+        assert_eq!(magnet.exact_length, Some(10));
+        assert_eq!(magnet.trackers, ["url", "other"]);
+        assert_eq!(magnet.web_seeds, ["seed"]);
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt;
+
+use trendt_bencode::Error;
+
+use crate::info_hash::to_hex;
+use crate::torrent::{Info, invalid};
+
+/// Length of a SHA-1 piece hash
+const HASH_LENGTH: usize = 20;
+
+/// The SHA-1 of one piece of a v1 torrent
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PieceHash(pub [u8; HASH_LENGTH]);
+
+impl fmt::Display for PieceHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&to_hex(&self.0))
+    }
+}
+
+impl fmt::Debug for PieceHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PieceHash({})", self)
+    }
+}
+
+impl Info {
+    /// Number of pieces
+    pub fn piece_count(&self) -> usize {
+        self.pieces.len() / HASH_LENGTH
+    }
+
+    /// The hash of each piece, in order
+    pub fn piece_hashes(&self) -> impl ExactSizeIterator<Item = PieceHash> + '_ {
+        self.pieces
+            .chunks_exact(HASH_LENGTH)
+            .map(|hash| PieceHash(hash.try_into().unwrap()))
+    }
+
+    /// The hash of piece `index`
+    pub fn piece_hash(&self, index: usize) -> Option<PieceHash> {
+        self.piece_hashes().nth(index)
+    }
+
+    /// Size of piece `index`; every piece is `piece_length` except possibly the last
+    pub fn piece_size(&self, index: usize) -> Option<i64> {
+        let count = self.piece_count();
+        if index >= count {
+            return None;
+        }
+        let start = self.piece_length * index as i64;
+        Some((self.total_length() - start).min(self.piece_length))
+    }
+
+    /// Check that there is one hash for every piece of the content
+    pub(crate) fn validate_pieces(&self) -> Result<(), Error> {
+        if !self.pieces.len().is_multiple_of(HASH_LENGTH) {
+            return invalid("pieces is not a multiple of 20 bytes");
+        }
+        let total = self.total_length();
+        let expected = total / self.piece_length + i64::from(total % self.piece_length != 0);
+        if self.piece_count() as i64 != expected {
+            return invalid(&format!(
+                "{} piece hashes for {} pieces",
+                self.piece_count(),
+                expected
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use trendt_bencode::{Value, bencode, encode};
+
+    use super::*;
+    use crate::torrent::Torrent;
+
+    fn parse(length: i64, pieces: &[u8]) -> Result<Torrent, Error> {
+        Torrent::from_bytes(&encode(&bencode! {
+            "announce" => "http://tracker.example/announce",
+            "info" => {
+                "length" => length,
+                "name" => "a.bin",
+                "piece length" => 16,
+                "pieces" => Value::from(pieces),
+            },
+        }))
+    }
+
+    #[test]
+    fn short_last_piece() {
+        let pieces: Vec<u8> = (0..60).collect();
+        let torrent = parse(40, &pieces).unwrap();
+        let info = &torrent.info;
+        assert_eq!(info.piece_count(), 3);
+        assert_eq!(
+            info.piece_hashes()
+                .map(|hash| hash.0[0])
+                .collect::<Vec<_>>(),
+            [0, 20, 40]
+        );
+        assert_eq!(info.piece_hash(1).unwrap().0, pieces[20..40]);
+        assert_eq!(
+            info.piece_hash(0).unwrap().to_string(),
+            "000102030405060708090a0b0c0d0e0f10111213"
+        );
+        assert_eq!(info.piece_hash(3), None);
+        assert_eq!(info.piece_size(0), Some(16));
+        assert_eq!(info.piece_size(1), Some(16));
+        assert_eq!(info.piece_size(2), Some(8));
+        assert_eq!(info.piece_size(3), None);
+    }
+
+    #[test]
+    fn exact_multiple_and_empty() {
+        let torrent = parse(32, &[0; 40]).unwrap();
+        assert_eq!(torrent.info.piece_size(1), Some(16));
+
+        let torrent = parse(0, &[]).unwrap();
+        assert_eq!(torrent.info.piece_count(), 0);
+        assert_eq!(torrent.info.piece_size(0), None);
+    }
+
+    #[test]
+    fn inconsistent_pieces_are_rejected() {
+        let err = parse(40, &[0; 50]).unwrap_err();
+        assert!(err.to_string().contains("multiple of 20"), "{}", err);
+        let err = parse(40, &[0; 40]).unwrap_err();
+        assert!(
+            err.to_string().contains("2 piece hashes for 3 pieces"),
+            "{}",
+            err
+        );
+        assert!(parse(40, &[0; 80]).is_err());
+    }
+}
@@ -0,0 +1,21 @@
+//! Test data shared by the unit tests of several modules
+
+/// Deterministic file content, different for every `seed`
+pub fn content(length: usize, seed: u8) -> Vec<u8> {
+    (0..length)
+        .map(|i| (i as u8).wrapping_mul(31) ^ seed)
+        .collect()
+}
+
+/// Info dictionary of a private single-file torrent holding `a.txt`
+pub const INFO: &[u8] =
+    b"d6:lengthi10e4:name5:a.txt12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaa7:privatei1ee";
+
+/// Info-hash of [`INFO`]
+pub const INFO_HASH: &str = "d2805498116a168a21466afc2640d84893bb7ef5";
+
+/// A torrent file with [`INFO`] under `info`, between the encoded `before`
+/// and `after` keys
+pub fn torrent(before: &[u8], after: &[u8]) -> Vec<u8> {
+    [b"d", before, b"4:info", INFO, after, b"e"].concat()
+}
@@ -1,9 +1,20 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_bytes::{ByteArray, ByteBuf};
+use trendt_bencode::{DecoderOptions, Error, RawBencode, Value};
 
+use crate::info_hash::{InfoHash, InfoHashV2};
+use crate::v2::FileTreeNode;
+
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Deserialize)]
+/// A parsed .torrent file
+///
+/// Only [`from_bytes`](Torrent::from_bytes), [`from_file`](Torrent::from_file)
+/// and [`TorrentBuilder`](crate::TorrentBuilder) create one, so the info-hash
+/// always matches the info dictionary.
+#[derive(Debug, Serialize)]
 pub struct Torrent {
     /// Primary tracker URL
     pub announce: String,
@@ -25,19 +36,112 @@ pub struct Torrent {
 
     /// File metadata and piece hashes
     pub info: Info,
+
+    /// v2 only: each file's piece-level merkle hashes, keyed by its `pieces root`
+    #[serde(
+        rename = "piece layers",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub piece_layers: BTreeMap<ByteArray<32>, ByteBuf>,
+
+    /// Keys not covered above, kept so the torrent can be written back out
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+
+    /// SHA-1 of the info dictionary as it appeared in the file
+    #[serde(skip)]
+    pub(crate) info_hash: InfoHash,
+
+    /// SHA-256 of the info dictionary, for v2 torrents
+    #[serde(skip)]
+    pub(crate) info_hash_v2: Option<InfoHashV2>,
+}
+
+/// The keys of a .torrent file as read, with `info` still encoded for hashing
+#[derive(Deserialize)]
+struct TorrentFile<'a> {
+    announce: String,
+    #[serde(rename = "announce-list")]
+    announce_list: Option<Vec<Vec<String>>>,
+    #[serde(rename = "creation date")]
+    creation_date: Option<i64>,
+    comment: Option<String>,
+    #[serde(rename = "created by")]
+    created_by: Option<String>,
+    #[serde(borrow)]
+    info: RawBencode<'a>,
+    #[serde(rename = "piece layers", default)]
+    piece_layers: BTreeMap<ByteArray<32>, ByteBuf>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, Value>,
 }
 
 impl Torrent {
     /// Load and parse a .torrent file
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, trendt_bencode::Error> {
-        let bytes = fs::read(path)
-            .map_err(|e| trendt_bencode::Error::Message(format!("failed to read file: {}", e)))?;
-        trendt_bencode::from_bytes(&bytes)
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let bytes =
+            fs::read(path).map_err(|e| Error::Message(format!("failed to read file: {}", e)))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Parse the contents of a .torrent file
+    ///
+    /// Parsing is lenient, since files in the wild have unsorted keys or
+    /// trailing bytes. The info-hash still covers the info dictionary exactly
+    /// as it appears in `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let options = DecoderOptions::lenient();
+        let file: TorrentFile = trendt_bencode::from_bytes_with_options(bytes, options)?;
+        let raw_info = file.info.as_bytes();
+        let info: Info = trendt_bencode::from_bytes_with_options(raw_info, options)?;
+        info.validate()?;
+        let torrent = Torrent {
+            announce: file.announce,
+            announce_list: file.announce_list,
+            creation_date: file.creation_date,
+            comment: file.comment,
+            created_by: file.created_by,
+            info_hash: InfoHash::of(raw_info),
+            info_hash_v2: info.is_v2().then(|| InfoHashV2::of(raw_info)),
+            info,
+            piece_layers: file.piece_layers,
+            extra: file.extra,
+        };
+        torrent.verify_piece_layers()?;
+        Ok(torrent)
+    }
+
+    /// Encode as the contents of a .torrent file
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        trendt_bencode::to_bytes(self)
+    }
+
+    /// Write a .torrent file
+    ///
+    /// Unknown keys are written back as they were read, so a torrent loaded
+    /// and saved again keeps its info-hash unless `info` was changed.
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// The v1 info-hash, computed from the original bytes of the info dictionary
+    pub fn info_hash(&self) -> InfoHash {
+        self.info_hash
+    }
+
+    /// The v2 info-hash, for torrents with `meta version` 2
+    ///
+    /// Trackers and peer handshakes use its [truncated](InfoHashV2::truncated)
+    /// form.
+    pub fn info_hash_v2(&self) -> Option<InfoHashV2> {
+        self.info_hash_v2
     }
 }
 
 /// The info dictionary - contains file metadata and piece hashes
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Info {
     /// File or directory name
     pub name: String,
@@ -46,35 +150,353 @@ pub struct Info {
     #[serde(rename = "piece length")]
     pub piece_length: i64,
 
-    /// Concatenated SHA-1 hashes (20 bytes each)
-    #[serde(with = "serde_bytes")]
+    /// Concatenated SHA-1 hashes (20 bytes each); empty for v2-only torrents
+    #[serde(with = "serde_bytes", default, skip_serializing_if = "Vec::is_empty")]
     pub pieces: Vec<u8>,
 
     /// File size in bytes (single-file torrents only)
     pub length: Option<i64>,
+
+    /// Files in the directory `name` (multi-file torrents only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<FileEntry>,
+
+    /// Optional: 1 if peers may only come from the trackers (BEP 27)
+    pub private: Option<i64>,
+
+    /// v2 only: 2 for BitTorrent v2 (BEP 52)
+    #[serde(rename = "meta version")]
+    pub meta_version: Option<i64>,
+
+    /// v2 only: the files by path, as nested dictionaries
+    #[serde(rename = "file tree")]
+    pub file_tree: Option<BTreeMap<String, FileTreeNode>>,
+
+    /// Keys not covered above; they are part of the info-hash, so must survive a round trip
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// One file of a multi-file torrent
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FileEntry {
+    /// File size in bytes
+    pub length: i64,
+
+    /// Path components below the torrent's directory
+    pub path: Vec<String>,
+
+    /// Optional: hex MD5 of the file contents
+    pub md5sum: Option<String>,
+
+    /// Optional: BEP 47 attribute flags, e.g. `p` for padding or `x` for executable
+    pub attr: Option<String>,
+
+    /// Keys not covered above, such as `sha1` or `path.utf-8`
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// Whether a torrent holds a single file or a directory of files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout<'a> {
+    /// One file called `name`
+    SingleFile { length: i64 },
+    /// A directory called `name` holding these files
+    MultiFile { files: &'a [FileEntry] },
+}
+
+/// Where a file sits in the concatenated content of a torrent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSpan {
+    /// Path relative to the download directory, starting with the torrent's `name`
+    pub path: PathBuf,
+    /// Offset of the file's first byte in the torrent
+    pub offset: i64,
+    /// File size in bytes
+    pub length: i64,
+}
+
+impl Info {
+    /// Whether the torrent is private, so DHT and peer exchange must not be used
+    pub fn is_private(&self) -> bool {
+        self.private == Some(1)
+    }
+
+    /// Whether the info dictionary has a v1 file layout, with `length` or `files`
+    pub fn is_v1(&self) -> bool {
+        self.length.is_some() || !self.files.is_empty()
+    }
+
+    /// Single or multi-file layout, as decided by which of `length` and `files` is set
+    pub fn layout(&self) -> Layout<'_> {
+        if self.files.is_empty() {
+            Layout::SingleFile {
+                length: self.length.unwrap_or(0),
+            }
+        } else {
+            Layout::MultiFile { files: &self.files }
+        }
+    }
+
+    /// Size of all files together
+    pub fn total_length(&self) -> i64 {
+        if !self.is_v1() {
+            return self.v2_files().iter().map(|(_, file)| file.length).sum();
+        }
+        match self.layout() {
+            Layout::SingleFile { length } => length,
+            Layout::MultiFile { files } => files.iter().map(|file| file.length).sum(),
+        }
+    }
+
+    /// Every file of the v1 layout with its path and offset, in torrent order
+    pub fn file_spans(&self) -> Vec<FileSpan> {
+        let name = PathBuf::from(&self.name);
+        match self.layout() {
+            Layout::SingleFile { length } => vec![FileSpan {
+                path: name,
+                offset: 0,
+                length,
+            }],
+            Layout::MultiFile { files } => {
+                let mut offset = 0;
+                files
+                    .iter()
+                    .map(|file| {
+                        let span = FileSpan {
+                            path: file
+                                .path
+                                .iter()
+                                .fold(name.clone(), |path, component| path.join(component)),
+                            offset,
+                            length: file.length,
+                        };
+                        offset += file.length;
+                        span
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Check that the layout is unambiguous, the piece hashes cover the content
+    /// and every path stays inside the download directory
+    ///
+    /// The v1 layout is checked unless the torrent is v2-only.
+    pub fn validate(&self) -> Result<(), Error> {
+        check_component(&self.name)?;
+        match self.meta_version {
+            None => {}
+            Some(2) => self.validate_v2()?,
+            Some(version) => return invalid(&format!("unsupported meta version {}", version)),
+        }
+        if self.is_v2() && !self.is_v1() {
+            return Ok(());
+        }
+
+        match (self.length, self.files.is_empty()) {
+            (Some(_), false) => return invalid("info has both length and files"),
+            (None, true) => return invalid("info has neither length nor files"),
+            _ => {}
+        }
+        if self.length.is_some_and(|length| length < 0)
+            || self.files.iter().any(|file| file.length < 0)
+        {
+            return invalid("file length is negative");
+        }
+        if self.total_length_checked().is_none() {
+            return invalid("total length overflows");
+        }
+        if self.piece_length <= 0 {
+            return invalid("piece length must be positive");
+        }
+        self.validate_pieces()?;
+
+        for file in &self.files {
+            if file.path.is_empty() {
+                return invalid("file path is empty");
+            }
+            for component in &file.path {
+                check_component(component)?;
+            }
+        }
+        if self.is_hybrid() {
+            self.validate_hybrid()?;
+        }
+        Ok(())
+    }
+
+    fn total_length_checked(&self) -> Option<i64> {
+        self.files
+            .iter()
+            .try_fold(self.length.unwrap_or(0), |total, file| {
+                total.checked_add(file.length)
+            })
+    }
+}
+
+/// Reject path components that are empty, special or contain separators
+pub(crate) fn check_component(component: &str) -> Result<(), Error> {
+    if component.is_empty()
+        || component == "."
+        || component == ".."
+        || component.contains(['/', '\\', '\0'])
+    {
+        return invalid(&format!("unsafe path component {:?}", component));
+    }
+    Ok(())
+}
+
+pub(crate) fn invalid<T>(message: &str) -> Result<T, Error> {
+    Err(Error::Message(format!("invalid torrent: {}", message)))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_data;
+    use trendt_bencode::{Value, bencode, encode};
+
+    fn parse(info: Value) -> Result<Torrent, Error> {
+        Torrent::from_bytes(&encode(&bencode! {
+            "announce" => "http://tracker.example/announce",
+            "info" => info,
+        }))
+    }
 
     #[test]
-    fn parse_debian_torrent() {
-        // CARGO_MANIFEST_DIR points to the workspace root during tests
-        let manifest_dir = env!("CARGO_MANIFEST_DIR");
-        let torrent_path = format!("{}/../../tests/fixtures/debian.torrent", manifest_dir);
-
-        let torrent = Torrent::from_file(&torrent_path).expect("failed to parse torrent");
-
-        println!("Name: {}", torrent.info.name);
-        println!("Piece length: {}", torrent.info.piece_length);
-        println!(
-            "Pieces: {} bytes ({} pieces)",
-            torrent.info.pieces.len(),
-            torrent.info.pieces.len() / 20
+    fn single_file() {
+        let torrent = parse(bencode! {
+            "length" => 10,
+            "name" => "a.txt",
+            "piece length" => 16384,
+            "pieces" => &[0u8; 20],
+        })
+        .unwrap();
+        assert_eq!(torrent.info.layout(), Layout::SingleFile { length: 10 });
+        assert_eq!(torrent.info.total_length(), 10);
+        assert_eq!(
+            torrent.info.file_spans(),
+            [FileSpan {
+                path: PathBuf::from("a.txt"),
+                offset: 0,
+                length: 10
+            }]
         );
+    }
+
+    #[test]
+    fn multi_file() {
+        let torrent = parse(bencode! {
+            "files" => [
+                { "length" => 3, "md5sum" => "0123", "path" => ["a", "b.txt"] },
+                { "attr" => "p", "length" => 5, "path" => [".pad", "5"] },
+                { "length" => 7, "path" => ["c"] },
+            ],
+            "name" => "dir",
+            "piece length" => 16384,
+            "pieces" => &[0u8; 20],
+        })
+        .unwrap();
+        let info = &torrent.info;
+        assert!(matches!(info.layout(), Layout::MultiFile { files } if files.len() == 3));
+        assert_eq!(info.files[0].md5sum.as_deref(), Some("0123"));
+        assert_eq!(info.files[1].attr.as_deref(), Some("p"));
+        assert_eq!(info.total_length(), 15);
+
+        let spans = info.file_spans();
+        assert_eq!(spans[0].path, Path::new("dir/a/b.txt"));
+        assert_eq!(
+            spans.iter().map(|span| span.offset).collect::<Vec<_>>(),
+            [0, 3, 8]
+        );
+        assert_eq!(spans[2].length, 7);
+    }
+
+    #[test]
+    fn info_hash_covers_the_original_bytes() {
+        // Unknown keys are not modelled by `Info` but still count towards the hash
+        let torrent = Torrent::from_bytes(&test_data::torrent(b"8:announce3:url", b"")).unwrap();
+        assert_eq!(torrent.info_hash().to_hex(), test_data::INFO_HASH);
+    }
+
+    #[test]
+    fn accept_non_canonical_files() {
+        // Unsorted keys and trailing bytes, but the same info dictionary as above
+        let mut input = test_data::torrent(b"", b"8:announce3:url");
+        input.push(b'\n');
+        let torrent = Torrent::from_bytes(&input).unwrap();
+        assert_eq!(torrent.announce, "url");
+        assert_eq!(torrent.info_hash().to_hex(), test_data::INFO_HASH);
+    }
+
+    #[test]
+    fn edit_and_save() {
+        let input: &[u8] = b"d8:announce3:url10:created by5:maker4:infod5:filesld6:lengthi3e4:pathl1:ae4:sha120:aaaaaaaaaaaaaaaaaaaaee4:name3:dir12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaa6:source3:abce8:url-listl12:http://seed/ee";
+        let mut torrent = Torrent::from_bytes(input).unwrap();
+        assert_eq!(torrent.to_bytes().unwrap(), input);
+        assert_eq!(torrent.info.extra["source"].as_str(), Some("abc"));
+        assert!(torrent.info.files[0].extra.contains_key("sha1"));
+        assert!(torrent.extra.contains_key("url-list"));
+
+        torrent.announce = "http://tracker.example/announce".into();
+        torrent.announce_list = Some(vec![vec![torrent.announce.clone()]]);
+        let saved = Torrent::from_bytes(&torrent.to_bytes().unwrap()).unwrap();
+        assert_eq!(saved.info_hash(), torrent.info_hash());
+        assert_eq!(saved.announce, "http://tracker.example/announce");
+        assert_eq!(saved.extra, torrent.extra);
+
+        let path = std::env::temp_dir().join(format!("trendt-save-{}.torrent", std::process::id()));
+        saved.to_file(&path).unwrap();
+        let loaded = Torrent::from_file(&path).unwrap();
+        assert_eq!(loaded.info_hash(), torrent.info_hash());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn invalid_layouts() {
+        let info = |extra: Value| {
+            let mut info = bencode! {
+                "name" => "dir",
+                "piece length" => 16384,
+                "pieces" => &[0u8; 20],
+            };
+            for (key, value) in extra.as_dict().unwrap() {
+                info.insert(key.clone(), value.clone()).unwrap();
+            }
+            parse(info).unwrap_err().to_string()
+        };
+        assert!(info(bencode! {}).contains("neither length nor files"));
+        assert!(
+            info(bencode! { "files" => [{ "length" => 1, "path" => ["a"] }], "length" => 1 })
+                .contains("both")
+        );
+        assert!(info(bencode! { "length" => -1 }).contains("negative"));
+        assert!(info(bencode! { "files" => [{ "length" => 1, "path" => [] }] }).contains("empty"));
+        for path in ["..", "", "a/b", "."] {
+            assert!(
+                info(bencode! { "files" => [{ "length" => 1, "path" => ["x", path] }] })
+                    .contains("unsafe path"),
+                "{:?}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn parse_torrent_file() {
+        let path =
+            std::env::temp_dir().join(format!("trendt-parse-{}.torrent", std::process::id()));
+        fs::write(&path, test_data::torrent(b"8:announce3:url", b"")).unwrap();
+
+        let torrent = Torrent::from_file(&path).expect("failed to parse torrent");
+        fs::remove_file(&path).unwrap();
 
-        assert!(torrent.info.name.contains("debian"));
-        assert!(torrent.info.length.is_some());
+        assert_eq!(torrent.info.name, "a.txt");
+        assert_eq!(torrent.info.length, Some(10));
+        assert_eq!(torrent.info.piece_count(), 1);
+        assert_eq!(torrent.info_hash().to_hex(), test_data::INFO_HASH);
+        assert!(Torrent::from_file(&path).is_err());
     }
 }
@@ -0,0 +1,415 @@
+//! BitTorrent v2 metadata (BEP 52)
+//!
+//! v2 torrents describe their content as a `file tree` of dictionaries, and
+//! hash each file separately with SHA-256 merkle trees over 16 KiB blocks.
+//! Each file's tree root is stored in the info dictionary as its `pieces root`;
+//! for files longer than one piece, the layer of the tree at piece level goes
+//! in the outer `piece layers` dictionary.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use trendt_bencode::{Error, Value};
+
+use crate::torrent::{Info, Torrent, check_component, invalid};
+
+/// Size of the blocks hashed at the leaves of every merkle tree
+pub const BLOCK_SIZE: usize = 16 * 1024;
+
+/// A SHA-256 merkle tree node
+pub type MerkleHash = [u8; 32];
+
+/// An entry of a v2 file tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum FileTreeNode {
+    /// A file, whose properties sit under the empty key
+    File {
+        #[serde(rename = "")]
+        file: TreeFile,
+    },
+    /// A directory, by entry name
+    Directory(BTreeMap<String, FileTreeNode>),
+}
+
+/// A node is a file only when the empty key is its sole entry
+impl<'de> Deserialize<'de> for FileTreeNode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = FileTreeNode;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a file tree dictionary")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut file = None;
+        let mut entries = BTreeMap::new();
+        while let Some(name) = map.next_key::<String>()? {
+            if name.is_empty() {
+                file = Some(map.next_value::<TreeFile>()?);
+            } else {
+                entries.insert(name, map.next_value::<FileTreeNode>()?);
+            }
+        }
+        match file {
+            Some(file) if entries.is_empty() => Ok(FileTreeNode::File { file }),
+            Some(_) => Err(de::Error::custom(
+                "file entry \"\" must be the only key of its dictionary",
+            )),
+            None => Ok(FileTreeNode::Directory(entries)),
+        }
+    }
+}
+
+/// A file in a v2 file tree
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TreeFile {
+    /// File size in bytes
+    pub length: i64,
+
+    /// Root of the file's merkle tree; absent for empty files
+    #[serde(rename = "pieces root", default, with = "serde_bytes")]
+    pub pieces_root: Option<MerkleHash>,
+
+    /// Keys not covered above, such as `attr`
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+impl Info {
+    /// Whether the info dictionary has `meta version` 2
+    pub fn is_v2(&self) -> bool {
+        self.meta_version == Some(2)
+    }
+
+    /// Every file of the v2 file tree, in tree order
+    pub fn v2_files(&self) -> Vec<(Vec<String>, &TreeFile)> {
+        let mut files = Vec::new();
+        if let Some(tree) = &self.file_tree {
+            walk(tree, &mut Vec::new(), &mut files);
+        }
+        files
+    }
+
+    /// Check the v2 file tree
+    pub(crate) fn validate_v2(&self) -> Result<(), Error> {
+        if !self.piece_length.is_positive()
+            || !(self.piece_length as u64).is_power_of_two()
+            || (self.piece_length as usize) < BLOCK_SIZE
+        {
+            return invalid("v2 piece length must be a power of two of at least 16 KiB");
+        }
+        let Some(tree) = &self.file_tree else {
+            return invalid("v2 info has no file tree");
+        };
+        check_directory(tree)?;
+
+        let mut total: i64 = 0;
+        for (path, file) in self.v2_files() {
+            if file.length < 0 {
+                return invalid("file length is negative");
+            }
+            let Some(sum) = total.checked_add(file.length) else {
+                return invalid("total length overflows");
+            };
+            total = sum;
+            if file.pieces_root.is_some() != (file.length > 0) {
+                return invalid(&format!(
+                    "{} must have a pieces root exactly when it is not empty",
+                    path.join("/")
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Collect the files below `directory`, depth first in key order
+fn walk<'a>(
+    directory: &'a BTreeMap<String, FileTreeNode>,
+    path: &mut Vec<String>,
+    files: &mut Vec<(Vec<String>, &'a TreeFile)>,
+) {
+    for (name, node) in directory {
+        path.push(name.clone());
+        match node {
+            FileTreeNode::File { file } => files.push((path.clone(), file)),
+            FileTreeNode::Directory(entries) => walk(entries, path, files),
+        }
+        path.pop();
+    }
+}
+
+fn check_directory(directory: &BTreeMap<String, FileTreeNode>) -> Result<(), Error> {
+    if directory.is_empty() {
+        return invalid("empty directory in file tree");
+    }
+    for (name, node) in directory {
+        check_component(name)?;
+        if let FileTreeNode::Directory(entries) = node {
+            check_directory(entries)?;
+        }
+    }
+    Ok(())
+}
+
+impl Torrent {
+    /// The piece-level hashes of the file with this `pieces root`
+    ///
+    /// Only files longer than one piece have a layer; a shorter file's single
+    /// piece hash is its `pieces root`.
+    pub fn piece_layer(&self, pieces_root: &MerkleHash) -> Option<Vec<MerkleHash>> {
+        let layer = self.piece_layers.get(pieces_root)?;
+        Some(
+            layer
+                .chunks_exact(32)
+                .map(|hash| hash.try_into().unwrap())
+                .collect(),
+        )
+    }
+
+    /// Check every file's piece layer against its `pieces root`
+    pub fn verify_piece_layers(&self) -> Result<(), Error> {
+        let piece_length = self.info.piece_length as usize;
+        for (path, file) in self.info.v2_files() {
+            let Some(root) = &file.pieces_root else {
+                continue;
+            };
+            if file.length as u64 <= piece_length as u64 {
+                continue;
+            }
+            let path = path.join("/");
+            let Some(layer) = self.piece_layers.get(root) else {
+                return invalid(&format!("no piece layer for {}", path));
+            };
+            let pieces = (file.length as u64).div_ceil(piece_length as u64) as usize;
+            if layer.len() != pieces * 32 {
+                return invalid(&format!("piece layer of {} has the wrong length", path));
+            }
+            if layer_root(&self.piece_layer(root).unwrap(), piece_length) != *root {
+                return invalid(&format!(
+                    "piece layer of {} does not match its pieces root",
+                    path
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The `pieces root` of a file with this content
+pub fn file_root(data: &[u8]) -> MerkleHash {
+    let blocks: Vec<_> = data.chunks(BLOCK_SIZE).map(sha256).collect();
+    let width = blocks.len().next_power_of_two();
+    merkle_root(blocks, width, [0; 32])
+}
+
+/// The hash of each piece of a file, as stored in `piece layers`
+///
+/// The last piece is padded with zero leaves to a full piece.
+pub fn piece_layer(data: &[u8], piece_length: usize) -> Vec<MerkleHash> {
+    data.chunks(piece_length)
+        .map(|piece| {
+            let blocks = piece.chunks(BLOCK_SIZE).map(sha256).collect();
+            merkle_root(blocks, piece_length / BLOCK_SIZE, [0; 32])
+        })
+        .collect()
+}
+
+/// The `pieces root` implied by the piece layer of a file longer than one piece
+pub fn layer_root(layer: &[MerkleHash], piece_length: usize) -> MerkleHash {
+    // Pieces past the end of the file hash as if made of zero leaves
+    let mut pad = [0; 32];
+    let mut width = BLOCK_SIZE;
+    while width < piece_length {
+        pad = hash_pair(&pad, &pad);
+        width *= 2;
+    }
+    merkle_root(layer.to_vec(), layer.len().next_power_of_two(), pad)
+}
+
+/// Pad `layer` to `width` nodes and hash pairs up to a single root
+fn merkle_root(mut layer: Vec<MerkleHash>, width: usize, pad: MerkleHash) -> MerkleHash {
+    layer.resize(width.max(1), pad);
+    while layer.len() > 1 {
+        layer = layer
+            .chunks_exact(2)
+            .map(|pair| hash_pair(&pair[0], &pair[1]))
+            .collect();
+    }
+    layer[0]
+}
+
+fn hash_pair(left: &MerkleHash, right: &MerkleHash) -> MerkleHash {
+    Sha256::new()
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+fn sha256(data: &[u8]) -> MerkleHash {
+    Sha256::digest(data).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use trendt_bencode::{bencode, encode};
+
+    use super::*;
+    use crate::info_hash::InfoHashV2;
+    use crate::test_data::content;
+
+    const PIECE_LENGTH: usize = 2 * BLOCK_SIZE;
+
+    #[test]
+    fn merkle_roots() {
+        assert_eq!(file_root(b"abc"), sha256(b"abc"));
+
+        let data = content(BLOCK_SIZE + 1, 1);
+        let (a, b) = (sha256(&data[..BLOCK_SIZE]), sha256(&data[BLOCK_SIZE..]));
+        assert_eq!(file_root(&data), hash_pair(&a, &b));
+
+        // Three blocks pad to four with a zero leaf
+        let data = content(2 * BLOCK_SIZE + 1, 2);
+        let c = sha256(&data[2 * BLOCK_SIZE..]);
+        let expected = hash_pair(
+            &hash_pair(
+                &sha256(&data[..BLOCK_SIZE]),
+                &sha256(&data[BLOCK_SIZE..2 * BLOCK_SIZE]),
+            ),
+            &hash_pair(&c, &[0; 32]),
+        );
+        assert_eq!(file_root(&data), expected);
+
+        // For files longer than a piece, the root from the piece layer agrees
+        // with the root from the blocks
+        for length in [PIECE_LENGTH + 1, 2 * PIECE_LENGTH, 5 * PIECE_LENGTH - 3] {
+            let data = content(length, 3);
+            let layer = piece_layer(&data, PIECE_LENGTH);
+            assert_eq!(
+                layer_root(&layer, PIECE_LENGTH),
+                file_root(&data),
+                "{}",
+                length
+            );
+        }
+    }
+
+    /// A v2-only torrent with a small file, a large file and an empty one
+    fn torrent() -> (Value, Vec<u8>) {
+        let small = content(100, 4);
+        let large = content(3 * PIECE_LENGTH + 5, 5);
+        let info = bencode! {
+            "file tree" => {
+                "data" => {
+                    "large.bin" => { "" => { "length" => large.len() as i64, "pieces root" => &file_root(&large) } },
+                },
+                "empty" => { "" => { "length" => 0 } },
+                "small.txt" => { "" => { "length" => 100, "pieces root" => &file_root(&small) } },
+            },
+            "meta version" => 2,
+            "name" => "set",
+            "piece length" => PIECE_LENGTH as i64,
+        };
+        let layer = piece_layer(&large, PIECE_LENGTH).concat();
+        let torrent = bencode! {
+            "announce" => "http://tracker.example/announce",
+            "info" => info,
+            "piece layers" => { file_root(&large) => layer.clone() },
+        };
+        (torrent, layer)
+    }
+
+    #[test]
+    fn v2_only_torrent() {
+        let (value, layer) = torrent();
+        let bytes = encode(&value);
+        let torrent = Torrent::from_bytes(&bytes).unwrap();
+        let info = &torrent.info;
+        assert!(info.is_v2());
+        assert!(!info.is_v1());
+        assert_eq!(info.total_length(), 100 + 3 * PIECE_LENGTH as i64 + 5);
+
+        let files = info.v2_files();
+        let paths: Vec<_> = files.iter().map(|(path, _)| path.join("/")).collect();
+        assert_eq!(paths, ["data/large.bin", "empty", "small.txt"]);
+        assert_eq!(files[1].1.pieces_root, None);
+
+        let root = files[0].1.pieces_root.unwrap();
+        assert_eq!(torrent.piece_layer(&root).unwrap().concat(), layer);
+        assert_eq!(torrent.piece_layer(&files[2].1.pieces_root.unwrap()), None);
+
+        let v2 = torrent.info_hash_v2().unwrap();
+        assert_eq!(v2, InfoHashV2::of(&encode(&value["info"])));
+        assert_eq!(v2.truncated().as_bytes()[..], v2.as_bytes()[..20]);
+        let magnet = torrent.to_magnet();
+        assert_eq!((magnet.info_hash, magnet.info_hash_v2), (None, Some(v2)));
+        assert_eq!(torrent.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn bad_piece_layers_are_rejected() {
+        let (mut value, _) = torrent();
+        let layers = value["piece layers"].as_dict_mut().unwrap();
+        let (root, layer) = layers.pop_first().unwrap();
+        let mut changed = layer.as_bytes().unwrap().to_vec();
+        changed[0] ^= 1;
+        layers.insert(root.clone(), Value::from(changed));
+        let err = Torrent::from_bytes(&encode(&value)).unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{}", err);
+
+        value["piece layers"].as_dict_mut().unwrap().clear();
+        let err = Torrent::from_bytes(&encode(&value)).unwrap_err();
+        assert!(err.to_string().contains("no piece layer"), "{}", err);
+    }
+
+    #[test]
+    fn invalid_trees() {
+        let check = |info: Value| {
+            let value = bencode! { "announce" => "url", "info" => info };
+            Torrent::from_bytes(&encode(&value))
+                .unwrap_err()
+                .to_string()
+        };
+        let info = |tree: Value, piece_length: i64| {
+            bencode! {
+                "file tree" => tree,
+                "meta version" => 2,
+                "name" => "x",
+                "piece length" => piece_length,
+            }
+        };
+        let file = bencode! { "a" => { "" => { "length" => 1, "pieces root" => &[0u8; 32] } } };
+        assert!(check(info(file.clone(), 1000)).contains("power of two"));
+        assert!(check(info(bencode! {}, 16384)).contains("empty directory"));
+        assert!(
+            check(info(bencode! { "a" => { "" => { "length" => 1 } } }, 16384))
+                .contains("pieces root")
+        );
+        assert!(check(info(bencode! { ".." => file["a"].clone() }, 16384)).contains("unsafe"));
+        let mixed = bencode! {
+            "a" => {
+                "" => { "length" => 1, "pieces root" => &[0u8; 32] },
+                "b" => { "" => { "length" => 1, "pieces root" => &[0u8; 32] } },
+            }
+        };
+        assert!(check(info(mixed, 16384)).contains("must be the only key"));
+        let no_length = bencode! { "a" => { "" => { "pieces root" => &[0u8; 32] } } };
+        assert!(check(info(no_length, 16384)).contains("missing field `length`"));
+
+        let mut bad_version = info(file, 16384);
+        bad_version.insert("meta version", Value::from(3)).unwrap();
+        assert!(check(bad_version).contains("meta version"));
+    }
+}